@@ -0,0 +1,227 @@
+#![no_std]
+
+/// 计算平均值的采样数
+pub const SAMPLES: usize = 16;
+
+/// 锂电池放电曲线，电压(mV)从高到低对应的电量百分比，中间按直线插值
+pub const DISCHARGE_CURVE: [(u16, u8); 11] = [
+    (4200, 100),
+    (4100, 92),
+    (4000, 80),
+    (3900, 66),
+    (3800, 48),
+    (3750, 36),
+    (3700, 22),
+    (3650, 12),
+    (3600, 6),
+    (3500, 2),
+    (3300, 0),
+];
+
+/// 电池检测电路的参数，随硬件版本设置
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct BatteryConfig {
+    /// 分压电阻，电池接上端，ADC 接两个电阻中间(Ω)
+    pub upper: u32,
+    pub lower: u32,
+    /// 电量低于这个百分比时提示
+    pub warning: u8,
+    /// 电压低于这个值(mV)时关机，保护电池
+    pub cutoff: u16,
+}
+
+impl BatteryConfig {
+    /// ADC 引脚电压换算成电池电压(mV)
+    pub fn battery_mv(&self, adc_mv: u16) -> u16 {
+        let mv = adc_mv as u64 * (self.upper as u64 + self.lower as u64) / self.lower as u64;
+        mv.min(u16::MAX as u64) as u16
+    }
+}
+
+/// 按放电曲线估算电量百分比
+pub fn percent(mv: u16) -> u8 {
+    let (full, _) = DISCHARGE_CURVE[0];
+    if mv >= full {
+        return 100;
+    }
+    DISCHARGE_CURVE
+        .windows(2)
+        .find(|pair| mv >= pair[1].0)
+        .map(|pair| {
+            let ((high_mv, high), (low_mv, low)) = (pair[0], pair[1]);
+            let span = (high - low) as u32 * (mv - low_mv) as u32 / (high_mv - low_mv) as u32;
+            low + span as u8
+        })
+        .unwrap_or(0)
+}
+
+/// 电池的状态
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum BatteryLevel {
+    Normal,
+    /// 电量低，屏幕上提示
+    Low,
+    /// 低于关机电压，需要关机
+    Critical,
+}
+
+/// 电池电压监测，取最近 [`SAMPLES`] 次采样的平均值
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Battery {
+    config: BatteryConfig,
+    samples: [u16; SAMPLES],
+    count: usize,
+    next: usize,
+}
+
+impl Battery {
+    pub const fn new(config: BatteryConfig) -> Self {
+        Battery {
+            config,
+            samples: [0; SAMPLES],
+            count: 0,
+            next: 0,
+        }
+    }
+
+    /// 加入一次 ADC 引脚电压(mV)的采样
+    pub fn push(&mut self, adc_mv: u16) {
+        self.samples[self.next] = self.config.battery_mv(adc_mv);
+        self.next = (self.next + 1) % SAMPLES;
+        self.count = (self.count + 1).min(SAMPLES);
+    }
+
+    /// 平均电池电压(mV)，还没有采样时为 None
+    pub fn voltage(&self) -> Option<u16> {
+        if self.count == 0 {
+            return None;
+        }
+        let sum: u32 = self.samples[..self.count].iter().map(|mv| *mv as u32).sum();
+        Some((sum / self.count as u32) as u16)
+    }
+
+    pub fn percent(&self) -> Option<u8> {
+        self.voltage().map(percent)
+    }
+
+    /// 采样数不够时不会返回 [`BatteryLevel::Critical`]，避免开机时误关机
+    pub fn level(&self) -> BatteryLevel {
+        let Some(mv) = self.voltage() else {
+            return BatteryLevel::Normal;
+        };
+        if mv < self.config.cutoff && self.count == SAMPLES {
+            BatteryLevel::Critical
+        } else if percent(mv) <= self.config.warning {
+            BatteryLevel::Low
+        } else {
+            BatteryLevel::Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两个电阻相同，电池电压是 ADC 引脚的两倍
+    const CONFIG: BatteryConfig = BatteryConfig {
+        upper: 100_000,
+        lower: 100_000,
+        warning: 10,
+        cutoff: 3400,
+    };
+
+    fn battery(mv: u16, samples: usize) -> Battery {
+        let mut battery = Battery::new(CONFIG);
+        for _ in 0..samples {
+            battery.push(mv / 2);
+        }
+        battery
+    }
+
+    #[test]
+    fn curve_endpoints() {
+        assert_eq!(percent(4200), 100);
+        assert_eq!(percent(4500), 100);
+        assert_eq!(percent(u16::MAX), 100);
+        assert_eq!(percent(3300), 0);
+        assert_eq!(percent(3000), 0);
+        assert_eq!(percent(0), 0);
+        for (mv, expected) in DISCHARGE_CURVE {
+            assert_eq!(percent(mv), expected, "{} mV", mv);
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(percent(4150), 96);
+        assert_eq!(percent(3775), 42);
+        assert_eq!(percent(3400), 1);
+        // 向下取整
+        assert_eq!(percent(4199), 99);
+        assert_eq!(percent(3301), 0);
+        let mut last = 0;
+        for mv in 3000..=4300 {
+            assert!(percent(mv) >= last, "{} mV", mv);
+            last = percent(mv);
+        }
+    }
+
+    #[test]
+    fn divider() {
+        assert_eq!(CONFIG.battery_mv(1850), 3700);
+        let config = BatteryConfig {
+            upper: 200_000,
+            ..CONFIG
+        };
+        assert_eq!(config.battery_mv(1000), 3000);
+        // 超出范围时停在最大值
+        assert_eq!(CONFIG.battery_mv(40_000), u16::MAX);
+    }
+
+    #[test]
+    fn smoothing() {
+        let mut battery = Battery::new(CONFIG);
+        assert_eq!(battery.voltage(), None);
+        assert_eq!(battery.percent(), None);
+        battery.push(2000);
+        battery.push(1900);
+        assert_eq!(battery.voltage(), Some(3900));
+        assert_eq!(battery.percent(), Some(66));
+        // 只保留最近的采样
+        for _ in 0..SAMPLES {
+            battery.push(1850);
+        }
+        assert_eq!(battery.voltage(), Some(3700));
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(Battery::new(CONFIG).level(), BatteryLevel::Normal);
+        assert_eq!(battery(3800, SAMPLES).level(), BatteryLevel::Normal);
+        // 10% 在 3650～3700 mV 之间
+        assert_eq!(battery(3640, SAMPLES).level(), BatteryLevel::Low);
+        assert_eq!(battery(3660, SAMPLES).level(), BatteryLevel::Normal);
+        // 高于关机电压但电量为 0 时只是提示
+        assert_eq!(battery(3450, SAMPLES).level(), BatteryLevel::Low);
+        assert_eq!(battery(3300, SAMPLES).level(), BatteryLevel::Critical);
+    }
+
+    /// 需要采满一轮、平均值低于关机电压才关机
+    #[test]
+    fn critical_needs_full_window() {
+        let mut empty = battery(3300, SAMPLES - 1);
+        assert_eq!(empty.level(), BatteryLevel::Low);
+        empty.push(1650);
+        assert_eq!(empty.level(), BatteryLevel::Critical);
+
+        // 一次很低的采样被平均掉
+        let mut glitch = battery(3800, SAMPLES);
+        glitch.push(0);
+        assert_eq!(glitch.voltage(), Some(3562));
+        assert_eq!(glitch.level(), BatteryLevel::Low);
+        let mut dip = battery(3800, SAMPLES);
+        dip.push(1500);
+        assert_eq!(dip.level(), BatteryLevel::Normal);
+    }
+}
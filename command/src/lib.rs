@@ -0,0 +1,397 @@
+#![no_std]
+
+use core::fmt;
+use core::str::FromStr;
+
+use esp32c3_fm_preset::{Band, Entry, Preset, PRESET_COUNT};
+
+/// 一行命令最多的字符数
+pub const LINE_LEN: usize = 64;
+/// 音量寄存器 4 位，0000 最小，1111 最大
+pub const MAX_VOLUME: u8 = 15;
+/// 可以调到的频率范围(kHz)
+pub const MIN_FREQUENCY: u32 = 87_000;
+pub const MAX_FREQUENCY: u32 = 108_000;
+
+/// 串口命令
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Command {
+    Help,
+    /// 频率(kHz)
+    Tune(u32),
+    /// true 向上搜台
+    Seek(bool),
+    Volume(u8),
+    /// 预设编号从 0 开始，命令行里从 1 开始
+    PresetSave(u8),
+    PresetRecall(u8),
+    PresetList,
+    /// 以文本格式输出所有预设
+    PresetExport,
+    /// 导入一行文本格式的预设
+    PresetImport(u8, Preset),
+    RdsDump,
+    Status,
+    /// 打开或关闭遥测数据
+    Telemetry(bool),
+    /// 关闭收音机进入深度睡眠
+    Standby,
+    Reboot,
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum ParseError {
+    TooLong,
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+    ExtraArgument,
+}
+
+impl ParseError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            ParseError::TooLong => "line too long",
+            ParseError::UnknownCommand => "unknown command, try `help`",
+            ParseError::MissingArgument => "missing argument",
+            ParseError::InvalidArgument => "invalid argument",
+            ParseError::ExtraArgument => "too many arguments",
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// 解析一行命令，关键字不区分大小写
+pub fn parse(line: &str) -> Result<Command, ParseError> {
+    let mut args = line.split_whitespace();
+    let name = args.next().ok_or(ParseError::UnknownCommand)?;
+    let command = if keyword(name, "help") || name == "?" {
+        Command::Help
+    } else if keyword(name, "tune") {
+        let freq = number(args.next())?;
+        if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&freq) {
+            return Err(ParseError::InvalidArgument);
+        }
+        Command::Tune(freq)
+    } else if keyword(name, "seek") {
+        match args.next() {
+            Some(arg) if keyword(arg, "up") => Command::Seek(true),
+            Some(arg) if keyword(arg, "down") => Command::Seek(false),
+            Some(_) => return Err(ParseError::InvalidArgument),
+            None => return Err(ParseError::MissingArgument),
+        }
+    } else if keyword(name, "vol") {
+        let volume = number(args.next())?;
+        if volume > MAX_VOLUME {
+            return Err(ParseError::InvalidArgument);
+        }
+        Command::Volume(volume)
+    } else if keyword(name, "preset") {
+        match args.next() {
+            Some(arg) if keyword(arg, "save") => Command::PresetSave(preset(args.next())?),
+            Some(arg) if keyword(arg, "recall") => Command::PresetRecall(preset(args.next())?),
+            Some(arg) if keyword(arg, "list") => Command::PresetList,
+            Some(arg) if keyword(arg, "export") => Command::PresetExport,
+            // 电台名里可能有空格，剩下的部分整体作为一行
+            Some(arg) if keyword(arg, "import") => return import(rest(line, arg)),
+            Some(_) => return Err(ParseError::InvalidArgument),
+            None => return Err(ParseError::MissingArgument),
+        }
+    } else if keyword(name, "rds") {
+        match args.next() {
+            Some(arg) if keyword(arg, "dump") => Command::RdsDump,
+            Some(_) => return Err(ParseError::InvalidArgument),
+            None => return Err(ParseError::MissingArgument),
+        }
+    } else if keyword(name, "status") {
+        Command::Status
+    } else if keyword(name, "telemetry") {
+        match args.next() {
+            Some(arg) if keyword(arg, "on") => Command::Telemetry(true),
+            Some(arg) if keyword(arg, "off") => Command::Telemetry(false),
+            Some(_) => return Err(ParseError::InvalidArgument),
+            None => return Err(ParseError::MissingArgument),
+        }
+    } else if keyword(name, "standby") {
+        Command::Standby
+    } else if keyword(name, "reboot") {
+        Command::Reboot
+    } else {
+        return Err(ParseError::UnknownCommand);
+    };
+    if args.next().is_some() {
+        return Err(ParseError::ExtraArgument);
+    }
+    Ok(command)
+}
+
+/// `help` 命令输出的内容
+pub const HELP: &str = "\
+tune <kHz>
+seek up|down
+vol <0-15>
+preset save|recall <1-8>
+preset list
+preset export
+preset import <slot,frequency,band,pty,name>
+rds dump
+status
+telemetry on|off
+standby
+reboot";
+
+fn keyword(arg: &str, name: &str) -> bool {
+    arg.eq_ignore_ascii_case(name)
+}
+
+fn number<T: FromStr>(arg: Option<&str>) -> Result<T, ParseError> {
+    arg.ok_or(ParseError::MissingArgument)?
+        .parse()
+        .map_err(|_| ParseError::InvalidArgument)
+}
+
+fn preset(arg: Option<&str>) -> Result<u8, ParseError> {
+    let n: u8 = number(arg)?;
+    if n == 0 || n > PRESET_COUNT {
+        return Err(ParseError::InvalidArgument);
+    }
+    Ok(n - 1)
+}
+
+/// `arg` 之后的部分，`arg` 必须是 `line` 的一部分
+fn rest<'a>(line: &'a str, arg: &str) -> &'a str {
+    let end = arg.as_ptr() as usize - line.as_ptr() as usize + arg.len();
+    line[end..].trim()
+}
+
+/// 固件只支持默认的波段
+fn import(line: &str) -> Result<Command, ParseError> {
+    let entry = Entry::parse(line)
+        .map_err(|_| ParseError::InvalidArgument)?
+        .ok_or(ParseError::MissingArgument)?;
+    if entry.slot > PRESET_COUNT || entry.preset.band != Band::default() {
+        return Err(ParseError::InvalidArgument);
+    }
+    Ok(Command::PresetImport(entry.slot - 1, entry.preset))
+}
+
+/// 把收到的字节拼成一行
+pub struct LineBuffer {
+    buf: [u8; LINE_LEN],
+    len: usize,
+    // 本行超长，丢弃到行尾
+    overflow: bool,
+}
+
+impl LineBuffer {
+    pub const fn new() -> Self {
+        LineBuffer {
+            buf: [0; LINE_LEN],
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// 收到回车或换行时解析整行，空行返回 None
+    pub fn push(&mut self, byte: u8) -> Option<Result<Command, ParseError>> {
+        match byte {
+            b'\r' | b'\n' => {
+                let line = &self.buf[..self.len];
+                let result = if self.overflow {
+                    Some(Err(ParseError::TooLong))
+                } else if line.iter().all(u8::is_ascii_whitespace) {
+                    None
+                } else {
+                    Some(
+                        core::str::from_utf8(line)
+                            .map_err(|_| ParseError::UnknownCommand)
+                            .and_then(parse),
+                    )
+                };
+                self.clear();
+                result
+            }
+            // 退格
+            0x08 | 0x7F => {
+                self.len = self.len.saturating_sub(1);
+                None
+            }
+            _ if self.len < LINE_LEN => {
+                self.buf[self.len] = byte;
+                self.len += 1;
+                None
+            }
+            _ => {
+                self.overflow = true;
+                None
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.overflow = false;
+    }
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(buffer: &mut LineBuffer, bytes: &[u8]) -> Option<Result<Command, ParseError>> {
+        let mut result = None;
+        for &byte in bytes {
+            if let Some(r) = buffer.push(byte) {
+                assert!(result.is_none(), "more than one line");
+                result = Some(r);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse("help"), Ok(Command::Help));
+        assert_eq!(parse("?"), Ok(Command::Help));
+        assert_eq!(parse("TUNE 98500"), Ok(Command::Tune(98_500)));
+        assert_eq!(parse("  seek   Up "), Ok(Command::Seek(true)));
+        assert_eq!(parse("seek down"), Ok(Command::Seek(false)));
+        assert_eq!(parse("vol 0"), Ok(Command::Volume(0)));
+        assert_eq!(parse("vol 15"), Ok(Command::Volume(MAX_VOLUME)));
+        assert_eq!(parse("preset save 1"), Ok(Command::PresetSave(0)));
+        assert_eq!(parse("preset recall 8"), Ok(Command::PresetRecall(7)));
+        assert_eq!(parse("preset list"), Ok(Command::PresetList));
+        assert_eq!(parse("preset export"), Ok(Command::PresetExport));
+        assert_eq!(parse("rds dump"), Ok(Command::RdsDump));
+        assert_eq!(parse("status"), Ok(Command::Status));
+        assert_eq!(parse("telemetry on"), Ok(Command::Telemetry(true)));
+        assert_eq!(parse("telemetry OFF"), Ok(Command::Telemetry(false)));
+        assert_eq!(parse("standby"), Ok(Command::Standby));
+        assert_eq!(parse("reboot"), Ok(Command::Reboot));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("tuned 98500"), Err(ParseError::UnknownCommand));
+        assert_eq!(parse(""), Err(ParseError::UnknownCommand));
+        assert_eq!(parse("tune"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("tune 98.5"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("tune 86999"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("tune 108001"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("tune 98500 1"), Err(ParseError::ExtraArgument));
+        assert_eq!(parse("seek left"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("vol 16"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("vol -1"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("preset save 0"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("preset save 9"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("preset"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("rds"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("status now"), Err(ParseError::ExtraArgument));
+    }
+
+    #[test]
+    fn import() {
+        let Ok(Command::PresetImport(slot, preset)) =
+            parse("preset import 2,98500,87-108,10,POP FM")
+        else {
+            panic!("not an import");
+        };
+        assert_eq!(slot, 1);
+        assert_eq!(preset.frequency, 98_500);
+        assert_eq!(preset.pty, 10);
+        // 名字里的空格和大小写保留
+        assert_eq!(preset.name(), "POP FM");
+        let Ok(Command::PresetImport(_, preset)) = parse("PRESET  Import   1,98500,87-108,0,A  B")
+        else {
+            panic!("not an import");
+        };
+        assert_eq!(preset.name(), "A  B");
+        assert_eq!(parse("preset import"), Err(ParseError::MissingArgument));
+        assert_eq!(
+            parse("preset import 9,98500,87-108,0,X"),
+            Err(ParseError::InvalidArgument)
+        );
+        // 固件只支持默认波段
+        assert_eq!(
+            parse("preset import 1,80000,76-91,0,X"),
+            Err(ParseError::InvalidArgument)
+        );
+        assert_eq!(
+            parse("preset import 1,98500"),
+            Err(ParseError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn rest_of_line() {
+        let line = "preset import  a b  ";
+        let arg = line.split_whitespace().nth(1).unwrap();
+        assert_eq!(rest(line, arg), "a b");
+        assert_eq!(rest(line, line.split_whitespace().nth(3).unwrap()), "");
+    }
+
+    #[test]
+    fn line_buffer() {
+        let mut buffer = LineBuffer::new();
+        assert_eq!(feed(&mut buffer, b"status\r"), Some(Ok(Command::Status)));
+        // 回车换行时换行是一个空行
+        assert_eq!(feed(&mut buffer, b"\n"), None);
+        assert_eq!(feed(&mut buffer, b"status\n"), Some(Ok(Command::Status)));
+        assert_eq!(feed(&mut buffer, b"   \r"), None);
+    }
+
+    #[test]
+    fn line_buffer_backspace() {
+        let mut buffer = LineBuffer::new();
+        assert_eq!(
+            feed(&mut buffer, b"vol 9\x08\x7f8\r"),
+            Some(Err(ParseError::UnknownCommand))
+        );
+        assert_eq!(
+            feed(&mut buffer, b"vol 9\x088\r"),
+            Some(Ok(Command::Volume(8)))
+        );
+        // 空行上退格不会出错
+        assert_eq!(
+            feed(&mut buffer, b"\x08\x08help\r"),
+            Some(Ok(Command::Help))
+        );
+    }
+
+    #[test]
+    fn line_buffer_overflow() {
+        let mut buffer = LineBuffer::new();
+        let mut long = [b'a'; LINE_LEN + 1];
+        long[LINE_LEN] = b'\r';
+        assert_eq!(feed(&mut buffer, &long[..LINE_LEN]), None);
+        assert_eq!(feed(&mut buffer, b"a"), None);
+        assert_eq!(feed(&mut buffer, b"\r"), Some(Err(ParseError::TooLong)));
+        // 超长的行丢弃后下一行正常
+        assert_eq!(feed(&mut buffer, b"help\r"), Some(Ok(Command::Help)));
+        // 刚好 LINE_LEN 个字符不算超长
+        let mut line = [b' '; LINE_LEN];
+        line[..6].copy_from_slice(b"status");
+        assert_eq!(feed(&mut buffer, &line), None);
+        assert_eq!(feed(&mut buffer, b"\r"), Some(Ok(Command::Status)));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut buffer = LineBuffer::new();
+        assert_eq!(
+            feed(&mut buffer, b"help\xff\r"),
+            Some(Err(ParseError::UnknownCommand))
+        );
+    }
+}
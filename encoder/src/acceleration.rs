@@ -0,0 +1,136 @@
+/// 旋钮加速曲线，把转动速度(步/秒)换算成每步的倍数
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum AccelerationProfile {
+    /// 不加速，每步倍数固定为 1
+    #[default]
+    None,
+    /// 速度超过 `threshold` 后，每多 `per_step` 步/秒倍数加 1
+    Linear {
+        threshold: f32,
+        per_step: f32,
+        max: u32,
+    },
+    /// 速度超过 `threshold` 后，每多 `per_double` 步/秒倍数翻倍
+    Exponential {
+        threshold: f32,
+        per_double: f32,
+        max: u32,
+    },
+    /// `(速度下限, 倍数)`，按速度升序排列，取速度达到的最后一项
+    Table(&'static [(f32, u32)]),
+}
+
+impl AccelerationProfile {
+    pub fn multiplier(&self, speed: f32) -> u32 {
+        let multiplier = match *self {
+            AccelerationProfile::None => 1,
+            AccelerationProfile::Linear {
+                threshold,
+                per_step,
+                max,
+            } => {
+                if speed <= threshold || per_step <= 0.0 {
+                    1
+                } else {
+                    let extra = ((speed - threshold) / per_step) as u32;
+                    extra.saturating_add(1).min(max)
+                }
+            }
+            AccelerationProfile::Exponential {
+                threshold,
+                per_double,
+                max,
+            } => {
+                if speed <= threshold || per_double <= 0.0 {
+                    1
+                } else {
+                    let doubles = ((speed - threshold) / per_double) as u32;
+                    1u32.checked_shl(doubles).unwrap_or(u32::MAX).min(max)
+                }
+            }
+            AccelerationProfile::Table(table) => table
+                .iter()
+                .take_while(|(min_speed, _)| speed >= *min_speed)
+                .last()
+                .map(|(_, multiplier)| *multiplier)
+                .unwrap_or(1),
+        };
+        multiplier.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn none_is_always_one() {
+        for speed in [0.0, 5.0, 1000.0, f32::INFINITY, f32::NAN] {
+            assert_eq!(AccelerationProfile::None.multiplier(speed), 1);
+        }
+    }
+
+    #[test]
+    fn linear() {
+        let profile = AccelerationProfile::Linear {
+            threshold: 5.0,
+            per_step: 3.0,
+            max: 10,
+        };
+        assert_eq!(profile.multiplier(0.0), 1);
+        assert_eq!(profile.multiplier(5.0), 1);
+        assert_eq!(profile.multiplier(7.9), 1);
+        assert_eq!(profile.multiplier(8.0), 2);
+        assert_eq!(profile.multiplier(14.0), 4);
+        assert_eq!(profile.multiplier(1000.0), 10);
+        assert_eq!(profile.multiplier(f32::INFINITY), 10);
+        assert_eq!(profile.multiplier(f32::NAN), 1);
+    }
+
+    #[test]
+    fn linear_without_slope() {
+        let profile = AccelerationProfile::Linear {
+            threshold: 5.0,
+            per_step: 0.0,
+            max: 10,
+        };
+        assert_eq!(profile.multiplier(100.0), 1);
+    }
+
+    #[test]
+    fn exponential() {
+        let profile = AccelerationProfile::Exponential {
+            threshold: 10.0,
+            per_double: 5.0,
+            max: 16,
+        };
+        assert_eq!(profile.multiplier(10.0), 1);
+        assert_eq!(profile.multiplier(14.9), 1);
+        assert_eq!(profile.multiplier(15.0), 2);
+        assert_eq!(profile.multiplier(20.0), 4);
+        assert_eq!(profile.multiplier(25.0), 8);
+        assert_eq!(profile.multiplier(30.0), 16);
+        assert_eq!(profile.multiplier(35.0), 16);
+        // 移位超过 32 位时不溢出
+        assert_eq!(profile.multiplier(1000.0), 16);
+        let unlimited = AccelerationProfile::Exponential {
+            threshold: 0.0,
+            per_double: 1.0,
+            max: u32::MAX,
+        };
+        assert_eq!(unlimited.multiplier(1000.0), u32::MAX);
+    }
+
+    #[test]
+    fn table() {
+        const TABLE: [(f32, u32); 3] = [(10.0, 2), (20.0, 5), (40.0, 10)];
+        let profile = AccelerationProfile::Table(&TABLE);
+        assert_eq!(profile.multiplier(0.0), 1);
+        assert_eq!(profile.multiplier(10.0), 2);
+        assert_eq!(profile.multiplier(39.9), 5);
+        assert_eq!(profile.multiplier(100.0), 10);
+        assert_eq!(AccelerationProfile::Table(&[]).multiplier(100.0), 1);
+        // 表中的 0 倍按 1 处理
+        assert_eq!(AccelerationProfile::Table(&[(0.0, 0)]).multiplier(1.0), 1);
+    }
+}
@@ -0,0 +1,5 @@
+#![no_std]
+
+pub mod acceleration;
+pub mod quadrature;
+pub mod rotation;
@@ -0,0 +1,272 @@
+/// 旋钮转动方向
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum WheelDirection {
+    Front,
+    Back,
+    NoState,
+}
+
+/// 编码器每个定位点(detent)对应的格雷码状态变化次数
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum StepMode {
+    /// 每个定位点 4 次变化，完整走完一个格雷码周期才算一步
+    Full,
+    /// 每个定位点 2 次变化，00 和 11 都是定位点
+    Half,
+    /// 每次有效变化都算一步
+    Quarter,
+}
+
+impl StepMode {
+    fn is_detent(&self, state: u8) -> bool {
+        match self {
+            StepMode::Full => state == REST_STATE,
+            StepMode::Half => state == REST_STATE || state == 0b00,
+            StepMode::Quarter => true,
+        }
+    }
+
+    /// 到达定位点时累计的变化次数至少为该值才输出一步，
+    /// 小于一半的说明是抖动或中途反转
+    fn threshold(&self) -> i8 {
+        match self {
+            StepMode::Full => 2,
+            StepMode::Half | StepMode::Quarter => 1,
+        }
+    }
+}
+
+// 状态为 (A << 1) | B，引脚上拉，静止时为 11
+// 正转: 11 -> 10 -> 00 -> 01 -> 11
+// 反转: 11 -> 01 -> 00 -> 10 -> 11
+const REST_STATE: u8 = 0b11;
+const INVALID: i8 = i8::MIN;
+
+// 下标为 (上一状态 << 2) | 当前状态，1 正转，-1 反转，0 未变化
+// A、B 同时变化说明丢了边沿，无法判断方向
+const TRANSITIONS: [i8; 16] = [
+    0, 1, -1, INVALID, //
+    -1, 0, INVALID, 1, //
+    1, INVALID, 0, -1, //
+    INVALID, -1, 1, 0, //
+];
+
+/// 查表法正交解码，不依赖采样次数和时序，抖动产生的来回变化会互相抵消
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct QuadratureDecoder {
+    mode: StepMode,
+    state: u8,
+    count: i8,
+    invalid_transitions: u32,
+}
+
+impl QuadratureDecoder {
+    pub const fn new(mode: StepMode) -> Self {
+        QuadratureDecoder {
+            mode,
+            state: REST_STATE,
+            count: 0,
+            invalid_transitions: 0,
+        }
+    }
+
+    /// 以当前电平作为初始状态
+    pub fn reset(&mut self, a_is_high: bool, b_is_high: bool) {
+        self.state = Self::encode(a_is_high, b_is_high);
+        self.count = 0;
+    }
+
+    pub fn mode(&self) -> StepMode {
+        self.mode
+    }
+
+    /// 非法跳变(A、B 同时变化)的次数
+    pub fn invalid_transitions(&self) -> u32 {
+        self.invalid_transitions
+    }
+
+    /// 输入最新的 A/B 电平，走完一个定位点时返回转动方向
+    pub fn update(&mut self, a_is_high: bool, b_is_high: bool) -> Option<WheelDirection> {
+        let state = Self::encode(a_is_high, b_is_high);
+        let delta = TRANSITIONS[((self.state << 2) | state) as usize];
+        self.state = state;
+        match delta {
+            0 => return None,
+            INVALID => self.invalid_transitions = self.invalid_transitions.wrapping_add(1),
+            _ => self.count = self.count.saturating_add(delta),
+        }
+        if !self.mode.is_detent(state) {
+            return None;
+        }
+        let count = self.count;
+        self.count = 0;
+        if count >= self.mode.threshold() {
+            Some(WheelDirection::Front)
+        } else if count <= -self.mode.threshold() {
+            Some(WheelDirection::Back)
+        } else {
+            None
+        }
+    }
+
+    fn encode(a_is_high: bool, b_is_high: bool) -> u8 {
+        ((a_is_high as u8) << 1) | b_is_high as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 正转一个完整周期的 (A, B) 电平，从静止状态 11 之后开始
+    const FORWARD: [(bool, bool); 4] = [(true, false), (false, false), (false, true), (true, true)];
+    const BACKWARD: [(bool, bool); 4] =
+        [(false, true), (false, false), (true, false), (true, true)];
+
+    fn feed(
+        decoder: &mut QuadratureDecoder,
+        levels: &[(bool, bool)],
+    ) -> [Option<WheelDirection>; 8] {
+        let mut steps = [None; 8];
+        for (step, (a, b)) in steps.iter_mut().zip(levels) {
+            *step = decoder.update(*a, *b);
+        }
+        steps
+    }
+
+    fn count(steps: &[Option<WheelDirection>], direction: WheelDirection) -> usize {
+        steps
+            .iter()
+            .filter(|step| **step == Some(direction))
+            .count()
+    }
+
+    #[test]
+    fn transition_table() {
+        for previous in 0..4u8 {
+            for current in 0..4u8 {
+                let delta = TRANSITIONS[((previous << 2) | current) as usize];
+                let changed = previous ^ current;
+                let expected = match changed {
+                    0 => 0,
+                    0b11 => INVALID,
+                    // 格雷码顺序 11 -> 10 -> 00 -> 01 -> 11 为正转
+                    _ => {
+                        let order = [0b11, 0b10, 0b00, 0b01];
+                        let from = order.iter().position(|s| *s == previous).unwrap();
+                        if order[(from + 1) % 4] == current {
+                            1
+                        } else {
+                            -1
+                        }
+                    }
+                };
+                assert_eq!(delta, expected, "{previous:02b} -> {current:02b}");
+            }
+        }
+    }
+
+    #[test]
+    fn full_cycle_is_one_step() {
+        let mut decoder = QuadratureDecoder::new(StepMode::Full);
+        let steps = feed(&mut decoder, &FORWARD);
+        assert_eq!(steps[..4], [None, None, None, Some(WheelDirection::Front)]);
+        let steps = feed(&mut decoder, &BACKWARD);
+        assert_eq!(steps[..4], [None, None, None, Some(WheelDirection::Back)]);
+        assert_eq!(decoder.invalid_transitions(), 0);
+    }
+
+    #[test]
+    fn bounce_cancels_out() {
+        let mut decoder = QuadratureDecoder::new(StepMode::Full);
+        // A 相抖动后回到静止状态，不算转动
+        let steps = feed(
+            &mut decoder,
+            &[(true, false), (true, true), (true, false), (true, true)],
+        );
+        assert_eq!(
+            count(&steps, WheelDirection::Front) + count(&steps, WheelDirection::Back),
+            0
+        );
+        // 转动中途每个边沿都抖一次，仍然只算一步
+        let bouncy = [
+            (true, false),
+            (true, true),
+            (true, false),
+            (false, false),
+            (true, false),
+            (false, false),
+            (false, true),
+            (true, true),
+        ];
+        let steps = feed(&mut decoder, &bouncy);
+        assert_eq!(count(&steps, WheelDirection::Front), 1);
+        assert_eq!(count(&steps, WheelDirection::Back), 0);
+        assert_eq!(decoder.invalid_transitions(), 0);
+    }
+
+    #[test]
+    fn skipped_edge_is_invalid() {
+        let mut decoder = QuadratureDecoder::new(StepMode::Full);
+        // 11 -> 00 -> 11 两次都丢了边沿，方向未知，不算转动
+        assert_eq!(decoder.update(false, false), None);
+        assert_eq!(decoder.invalid_transitions(), 1);
+        assert_eq!(decoder.update(true, true), None);
+        assert_eq!(decoder.invalid_transitions(), 2);
+        // 之后的转动不受影响
+        let steps = feed(&mut decoder, &FORWARD);
+        assert_eq!(count(&steps, WheelDirection::Front), 1);
+        assert_eq!(decoder.invalid_transitions(), 2);
+    }
+
+    #[test]
+    fn full_step_threshold() {
+        let mut decoder = QuadratureDecoder::new(StepMode::Full);
+        // 转了一个变化就反转回来，回到定位点时不足 2
+        assert_eq!(
+            feed(&mut decoder, &[(true, false), (true, true)])[..2],
+            [None, None]
+        );
+        // 两个有效变化后跳回定位点(中间丢了边沿)，达到阈值
+        assert_eq!(decoder.update(true, false), None);
+        assert_eq!(decoder.update(false, false), None);
+        assert_eq!(decoder.update(true, true), Some(WheelDirection::Front));
+        assert_eq!(decoder.invalid_transitions(), 1);
+    }
+
+    #[test]
+    fn half_step_threshold() {
+        let mut decoder = QuadratureDecoder::new(StepMode::Half);
+        // 00 和 11 都是定位点，一个周期两步
+        let steps = feed(&mut decoder, &FORWARD);
+        assert_eq!(
+            steps[..4],
+            [
+                None,
+                Some(WheelDirection::Front),
+                None,
+                Some(WheelDirection::Front)
+            ]
+        );
+        let steps = feed(&mut decoder, &BACKWARD);
+        assert_eq!(
+            steps[..4],
+            [
+                None,
+                Some(WheelDirection::Back),
+                None,
+                Some(WheelDirection::Back)
+            ]
+        );
+        // 10 不是定位点，来回抖动不输出
+        let steps = feed(&mut decoder, &[(true, false), (true, true)]);
+        assert_eq!(steps[..2], [None, None]);
+    }
+
+    #[test]
+    fn quarter_step_every_change() {
+        let mut decoder = QuadratureDecoder::new(StepMode::Quarter);
+        let steps = feed(&mut decoder, &FORWARD);
+        assert_eq!(count(&steps, WheelDirection::Front), 4);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::quadrature::WheelDirection;
+
+//转动时判断方向是否一致，
+//一致则判断last_time是否过久，过久则重记时间
+//方向一致且时间不过久则步长加1 ，更新 last_time ,并用 步数 / 时间得到速度，
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct RotateState {
+    pub begin_timestamp: u64,
+    pub last_timestamp: u64,
+    pub wheel_direction: WheelDirection,
+    pub steps: u32,
+}
+
+impl RotateState {
+    pub const fn new() -> Self {
+        RotateState {
+            begin_timestamp: 0,
+            last_timestamp: 0,
+            wheel_direction: WheelDirection::NoState,
+            steps: 0,
+        }
+    }
+
+    /// 在 `ms` 时刻向 `wheel_direction` 转动了一步
+    pub fn do_step(&mut self, wheel_direction: WheelDirection, ms: u64, speed_delay: u64) {
+        if self.wheel_direction == wheel_direction
+            && ms.saturating_sub(self.last_timestamp) < speed_delay
+        {
+            self.last_timestamp = ms;
+            self.steps += 1;
+            return;
+        }
+        self.wheel_direction = wheel_direction;
+        self.begin_timestamp = ms;
+        self.last_timestamp = ms;
+        self.steps = 1;
+    }
+
+    pub fn speed(&self) -> f32 {
+        if self.steps > 3 {
+            // 多步落在同一毫秒内时按 1ms 计算，避免除以 0
+            let elapsed = self
+                .last_timestamp
+                .saturating_sub(self.begin_timestamp)
+                .max(1);
+            self.steps as f32 / elapsed as f32 * 1000.0
+        } else {
+            self.steps as f32
+        }
+    }
+}
+
+impl Default for RotateState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn few_steps_report_step_count() {
+        let mut state = RotateState::new();
+        state.do_step(WheelDirection::Front, 1000, 300);
+        assert_eq!(state.speed(), 1.0);
+        state.do_step(WheelDirection::Front, 1100, 300);
+        state.do_step(WheelDirection::Front, 1200, 300);
+        assert_eq!(state.speed(), 3.0);
+    }
+
+    #[test]
+    fn speed_in_steps_per_second() {
+        let mut state = RotateState::new();
+        for ms in [1000, 1010, 1020, 1030, 1040] {
+            state.do_step(WheelDirection::Back, ms, 300);
+        }
+        assert_eq!(state.steps, 5);
+        assert_eq!(state.speed(), 125.0);
+    }
+
+    #[test]
+    fn same_millisecond_does_not_divide_by_zero() {
+        let mut state = RotateState::new();
+        for _ in 0..5 {
+            state.do_step(WheelDirection::Front, 1000, 300);
+        }
+        let speed = state.speed();
+        assert!(speed.is_finite());
+        assert_eq!(speed, 5000.0);
+    }
+
+    #[test]
+    fn restart_on_direction_change_or_pause() {
+        let mut state = RotateState::new();
+        for ms in [1000, 1010, 1020, 1030] {
+            state.do_step(WheelDirection::Front, ms, 300);
+        }
+        state.do_step(WheelDirection::Back, 1040, 300);
+        assert_eq!((state.steps, state.begin_timestamp), (1, 1040));
+        state.do_step(WheelDirection::Back, 1340, 300);
+        assert_eq!((state.steps, state.begin_timestamp), (1, 1340));
+        // 时间倒退时不会下溢
+        state.do_step(WheelDirection::Back, 1000, 300);
+        assert_eq!(state.steps, 2);
+    }
+}
@@ -0,0 +1,159 @@
+/// 一个字段的值
+#[derive(PartialEq, Debug)]
+pub enum Value<'a> {
+    /// 去掉引号、处理过转义的字符串
+    String(String),
+    /// 数字、true/false 和 null
+    Literal(&'a str),
+}
+
+/// 从一层的 JSON 对象里取出字段的值
+///
+/// 只处理固件和本工具自己输出的格式：字段的值没有嵌套的对象和数组
+pub fn field<'a>(object: &'a str, key: &str) -> Option<Value<'a>> {
+    let mut rest = object.trim_start().strip_prefix('{')?;
+    loop {
+        rest = rest.trim_start();
+        let (name, after) = string(rest.strip_prefix('"')?)?;
+        rest = after.trim_start().strip_prefix(':')?.trim_start();
+        let value = match rest.strip_prefix('"') {
+            Some(text) => {
+                let (text, after) = string(text)?;
+                rest = after;
+                Value::String(text)
+            }
+            None => {
+                let end = rest.find([',', '}'])?;
+                let literal = rest[..end].trim();
+                rest = &rest[end..];
+                Value::Literal(literal)
+            }
+        };
+        if name == key {
+            return Some(value);
+        }
+        rest = rest.trim_start().strip_prefix(',')?;
+    }
+}
+
+/// 字符串类型的字段
+pub fn string_field(object: &str, key: &str) -> Option<String> {
+    match field(object, key)? {
+        Value::String(text) => Some(text),
+        Value::Literal(_) => None,
+    }
+}
+
+/// 数字、true/false 和 null 类型的字段
+pub fn literal_field<'a>(object: &'a str, key: &str) -> Option<&'a str> {
+    match field(object, key)? {
+        Value::Literal(literal) => Some(literal),
+        Value::String(_) => None,
+    }
+}
+
+/// 分出数组里的每个对象，字符串里的 `{` `}` 不算
+pub fn objects(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let start = rest.find('{')?;
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        for (i, c) in rest[start..].char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '{' if !in_string => depth += 1,
+                '}' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let end = start + i + 1;
+                        let object = &rest[start..end];
+                        rest = &rest[end..];
+                        return Some(object);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // 没有结束的对象
+        rest = "";
+        None
+    })
+}
+
+/// `text` 从开头引号之后开始，返回处理过转义的字符串和结束引号之后的部分
+fn string(text: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 1..])),
+            '\\' => {
+                let escaped = match chars.next()?.1 {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\x08',
+                    'f' => '\x0c',
+                    'u' => {
+                        let start = chars.next()?.0;
+                        let hex = text.get(start..start + 4)?;
+                        chars.nth(2)?;
+                        char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+                            .unwrap_or(char::REPLACEMENT_CHARACTER)
+                    }
+                    c => c,
+                };
+                value.push(escaped);
+            }
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let object = r#"{"t":"rds", "ms": 1200,"ok":true,"ps":null,"name":"a,\"b\"}{\\"}"#;
+        assert_eq!(field(object, "t"), Some(Value::String("rds".to_string())));
+        assert_eq!(literal_field(object, "ms"), Some("1200"));
+        assert_eq!(literal_field(object, "ok"), Some("true"));
+        assert_eq!(literal_field(object, "ps"), Some("null"));
+        assert_eq!(string_field(object, "ps"), None);
+        assert_eq!(string_field(object, "name").as_deref(), Some(r#"a,"b"}{\"#));
+        assert_eq!(field(object, "missing"), None);
+    }
+
+    #[test]
+    fn key_inside_string() {
+        let object = r#"{"name":"\"pty\":3","pty":10}"#;
+        assert_eq!(literal_field(object, "pty"), Some("10"));
+    }
+
+    #[test]
+    fn escapes() {
+        let object = r#"{"s":"\n\t\/Aé"}"#;
+        assert_eq!(string_field(object, "s").as_deref(), Some("\n\t/Aé"));
+        assert_eq!(field(r#"{"s":"\u00"}"#, "s"), None);
+        assert_eq!(field(r#"{"s":"open}"#, "s"), None);
+    }
+
+    #[test]
+    fn split_objects() {
+        let text = r#"[{"name":"}{"}, {"name":"a\"}"} ,{"name":"b"}"#;
+        let split: Vec<&str> = objects(text).collect();
+        assert_eq!(
+            split,
+            [r#"{"name":"}{"}"#, r#"{"name":"a\"}"}"#, r#"{"name":"b"}"#]
+        );
+        assert_eq!(objects("[]").count(), 0);
+        assert_eq!(objects(r#"[{"name":"}"#).count(), 0);
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 等待一条命令结束的最长时间，搜台最慢
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// 收音机没有在规定时间内回复
+    Timeout,
+    /// 串口已关闭
+    Disconnected,
+    /// 收音机回复的错误
+    Radio(String),
+    /// 回复或文件的格式不对
+    Format(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io: {}", e),
+            Error::Timeout => f.write_str("radio did not answer"),
+            Error::Disconnected => f.write_str("serial port closed"),
+            Error::Radio(message) => write!(f, "radio: {}", message),
+            Error::Format(message) => write!(f, "format: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// 和收音机的串口命令行对话
+///
+/// 固件执行完一条命令后输出 `ok` 或 `error: ...`，中间的行是命令的输出。
+/// 发送命令前收到的行丢弃，命令执行期间的遥测数据和日志不算作输出
+pub struct Link {
+    writer: Box<dyn Write + Send>,
+    lines: Receiver<String>,
+}
+
+impl Link {
+    pub fn new(writer: Box<dyn Write + Send>, lines: Receiver<String>) -> Self {
+        Link { writer, lines }
+    }
+
+    /// 打开 USB 串口，例如 Linux 上的 `/dev/ttyACM0`
+    pub fn open(path: &str) -> Result<Self, Error> {
+        // USB 串口不需要设置波特率，只需要关掉终端的回显和行缓冲
+        let status = Command::new("stty")
+            .args(["-F", path, "raw", "-echo"])
+            .status()?;
+        if !status.success() {
+            return Err(Error::Io(io::Error::other("stty failed")));
+        }
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let reader = file.try_clone()?;
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || read_lines(reader, sender));
+        Ok(Link::new(Box::new(file), lines))
+    }
+
+    /// 发送一条命令，返回 `ok` 之前的输出
+    pub fn command(&mut self, command: &str) -> Result<Vec<String>, Error> {
+        while self.lines.try_recv().is_ok() {}
+        write!(self.writer, "{}\r", command)?;
+        self.writer.flush()?;
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        let mut output = Vec::new();
+        loop {
+            let line = self.next_line(Some(deadline))?;
+            // 回显、遥测数据和日志
+            if line == command || line.starts_with('{') || is_log(&line) {
+                continue;
+            }
+            if line == "ok" {
+                return Ok(output);
+            }
+            if let Some(message) = line.strip_prefix("error: ") {
+                return Err(Error::Radio(message.to_string()));
+            }
+            output.push(line);
+        }
+    }
+
+    /// 读取下一行，`deadline` 为 None 时一直等待
+    pub fn next_line(&mut self, deadline: Option<Instant>) -> Result<String, Error> {
+        let Some(deadline) = deadline else {
+            return self.lines.recv().map_err(|_| Error::Disconnected);
+        };
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => Error::Timeout,
+            RecvTimeoutError::Disconnected => Error::Disconnected,
+        })
+    }
+}
+
+/// 固件 `log` 输出的一行，例如 `\x1b[32mINFO - start\x1b[0m`，颜色可以没有
+fn is_log(line: &str) -> bool {
+    let line = match line.strip_prefix("\x1b[") {
+        Some(rest) => rest.split_once('m').map_or(rest, |(_, rest)| rest),
+        None => line,
+    };
+    ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"]
+        .iter()
+        .any(|level| {
+            line.strip_prefix(level)
+                .is_some_and(|rest| rest.starts_with(" - "))
+        })
+}
+
+fn read_lines(reader: File, sender: Sender<String>) {
+    for line in BufReader::new(reader).split(b'\n') {
+        let Ok(line) = line else {
+            return;
+        };
+        let line = String::from_utf8_lossy(&line)
+            .trim_end_matches('\r')
+            .to_string();
+        if sender.send(line).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_lines() {
+        assert!(is_log("\x1b[32mINFO - start\x1b[0m"));
+        assert!(is_log("\x1b[31mERROR - i2c: nack\x1b[0m"));
+        assert!(is_log("WARN - battery low: 3500 mV"));
+        assert!(!is_log("INFO"));
+        assert!(!is_log("1: 98500 kHz INFO - X"));
+        assert!(!is_log("error: invalid argument"));
+        assert!(!is_log("freq: 98500 kHz, rssi: 42"));
+    }
+}
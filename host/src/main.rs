@@ -0,0 +1,261 @@
+mod json;
+mod link;
+mod presets;
+mod sim;
+mod telemetry;
+
+use std::process::ExitCode;
+
+use esp32c3_fm_preset::Entry;
+
+use link::{Error, Link};
+use telemetry::Record;
+
+/// 搜台最多的次数，防止收音机一直搜不到起点
+const MAX_SCAN: usize = 100;
+
+const USAGE: &str = "\
+usage: radio-cli <port|--sim> <command>
+
+commands:
+  status               show frequency, rssi and volume
+  tune <kHz>           tune to a frequency
+  scan                 seek through the band and list the stations
+  export [--csv]       print the presets as JSON or CSV
+  import <file>        load presets from a .json or .csv file
+  stream               print RDS and RSSI until interrupted
+  raw <command...>     send any console command
+
+<port> is the USB serial device, e.g. /dev/ttyACM0.
+--sim talks to a simulated radio instead.";
+
+/// `status` 命令的结果
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Status {
+    frequency: u32,
+    rssi: u8,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(port), Some(command)) = (args.first(), args.get(1)) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let link = if port == "--sim" {
+        sim::spawn()
+    } else {
+        Link::open(port)
+    };
+    let result = link.and_then(|mut link| run(&mut link, command, &args[2..]));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(link: &mut Link, command: &str, args: &[String]) -> Result<(), Error> {
+    match (command, args) {
+        ("status", []) => {
+            for line in link.command("status")? {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+        ("tune", [freq]) => {
+            link.command(&format!("tune {}", freq))?;
+            let status = status(link)?;
+            println!("{} kHz, rssi {}", status.frequency, status.rssi);
+            Ok(())
+        }
+        ("scan", []) => {
+            for station in scan(link)? {
+                println!("{} kHz, rssi {}", station.frequency, station.rssi);
+            }
+            Ok(())
+        }
+        ("export", []) => {
+            print!("{}", presets::to_json(&export(link)?));
+            Ok(())
+        }
+        ("export", [format]) if format == "--csv" => {
+            print!("{}", presets::to_csv(&export(link)?));
+            Ok(())
+        }
+        ("import", [path]) => import(link, path),
+        ("stream", []) => stream(link),
+        ("raw", [_, ..]) => {
+            for line in link.command(&args.join(" "))? {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+        _ => Err(Error::Format(format!(
+            "unknown command `{}`\n{}",
+            command, USAGE
+        ))),
+    }
+}
+
+/// 解析 `status` 的输出，例如 `freq: 98500 kHz, rssi: 52, stereo: true, ...`
+fn status(link: &mut Link) -> Result<Status, Error> {
+    let output = link.command("status")?;
+    let line = output
+        .iter()
+        .find(|line| line.starts_with("freq: "))
+        .ok_or_else(|| Error::Format("no status line".to_string()))?;
+    let value = |key: &str| {
+        line.split(", ")
+            .find_map(|part| part.strip_prefix(key)?.strip_prefix(": "))
+            .map(|value| value.trim_end_matches(" kHz"))
+            .ok_or_else(|| Error::Format(format!("no {} in `{}`", key, line)))
+    };
+    let number = |text: &str| {
+        text.parse()
+            .map_err(|_| Error::Format(format!("`{}` is not a number", text)))
+    };
+    Ok(Status {
+        frequency: number(value("freq")?)?,
+        rssi: number(value("rssi")?)? as u8,
+    })
+}
+
+/// 从当前频率向上搜台，回到已经搜到的电台时结束，最后回到原来的频率
+fn scan(link: &mut Link) -> Result<Vec<Status>, Error> {
+    let start = status(link)?;
+    let mut stations: Vec<Status> = Vec::new();
+    for _ in 0..MAX_SCAN {
+        link.command("seek up")?;
+        let station = status(link)?;
+        if stations.iter().any(|s| s.frequency == station.frequency) {
+            break;
+        }
+        eprintln!("found {} kHz", station.frequency);
+        stations.push(station);
+    }
+    stations.sort_by_key(|s| s.frequency);
+    link.command(&format!("tune {}", start.frequency))?;
+    Ok(stations)
+}
+
+fn export(link: &mut Link) -> Result<Vec<Entry>, Error> {
+    presets::from_export(&link.command("preset export")?)
+}
+
+/// 逐行发送 `preset import`，收音机会检查每一行
+fn import(link: &mut Link, path: &str) -> Result<(), Error> {
+    let text = std::fs::read_to_string(path)?;
+    let entries = if path.ends_with(".csv") {
+        presets::from_csv(&text)?
+    } else {
+        presets::from_json(&text)?
+    };
+    for entry in &entries {
+        link.command(&format!("preset import {}", entry))?;
+        eprintln!(
+            "preset {}: {} kHz {}",
+            entry.slot,
+            entry.preset.frequency,
+            entry.preset.name()
+        );
+    }
+    Ok(())
+}
+
+/// 打开遥测，把 status 和 rds 记录转成一行一条的文字
+fn stream(link: &mut Link) -> Result<(), Error> {
+    link.command("telemetry on")?;
+    loop {
+        let line = link.next_line(None)?;
+        match telemetry::parse(&line) {
+            Some((_, Record::Hello { version })) if version != telemetry::SCHEMA_VERSION => {
+                eprintln!(
+                    "telemetry format v{}, expected v{}",
+                    version,
+                    telemetry::SCHEMA_VERSION
+                )
+            }
+            Some((
+                ms,
+                Record::Status {
+                    frequency,
+                    rssi,
+                    stereo,
+                    ..
+                },
+            )) => println!("{} freq {} rssi {} stereo {}", ms, frequency, rssi, stereo),
+            Some((
+                ms,
+                Record::Rds {
+                    pi,
+                    pty,
+                    program_service,
+                },
+            )) => println!(
+                "{} pi {} pty {} ps {:?}",
+                ms,
+                pi,
+                pty,
+                program_service.unwrap_or_default()
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESETS: &str = "\
+slot,frequency,band,pty,name
+1,98500,87-108,10,POP FM
+3,88100,87-108,1,NEWS
+";
+
+    #[test]
+    fn simulated_radio_over_pty() {
+        let mut link = sim::spawn().unwrap();
+        // 之后调台、搜台时收音机会输出遥测数据
+        link.command("telemetry on").unwrap();
+        assert!(export(&mut link).unwrap().is_empty());
+
+        let path = std::env::temp_dir().join(format!("radio-cli-{}.csv", std::process::id()));
+        std::fs::write(&path, PRESETS).unwrap();
+        let result = import(&mut link, path.to_str().unwrap());
+        std::fs::remove_file(&path).ok();
+        result.unwrap();
+        assert_eq!(
+            export(&mut link).unwrap(),
+            presets::from_csv(PRESETS).unwrap()
+        );
+
+        // 保存预设时固件输出一行日志
+        // 遥测数据和日志不算作命令的输出
+        assert!(link.command("tune 91500").unwrap().is_empty());
+        assert!(link.command("preset save 2").unwrap().is_empty());
+        let entries = export(&mut link).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].preset.frequency, 91_500);
+        assert_eq!(entries[1].preset.name(), "CLASSIC");
+
+        link.command("tune 87500").unwrap();
+        let frequencies: Vec<u32> = scan(&mut link)
+            .unwrap()
+            .iter()
+            .map(|s| s.frequency)
+            .collect();
+        assert_eq!(frequencies, [88_100, 91_500, 98_500, 103_900]);
+        assert_eq!(status(&mut link).unwrap().frequency, 87_500);
+        assert!(link.command("seek up").unwrap().is_empty());
+
+        assert!(matches!(link.command("vol 16"), Err(Error::Radio(_))));
+        assert!(matches!(
+            link.command("preset import 9,98500,87-108,0,X"),
+            Err(Error::Radio(_))
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt::Write;
+
+use esp32c3_fm_preset::{self as format, Entry};
+
+use crate::json;
+use crate::link::Error;
+
+pub use esp32c3_fm_preset::PRESET_COUNT;
+
+/// 解析 `preset export` 的输出
+pub fn from_export(lines: &[String]) -> Result<Vec<Entry>, Error> {
+    from_csv(&lines.join("\n"))
+}
+
+/// 和固件使用同一种文本格式
+pub fn to_csv(entries: &[Entry]) -> String {
+    format::to_text(entries)
+}
+
+pub fn from_csv(text: &str) -> Result<Vec<Entry>, Error> {
+    format::from_text(text).map_err(|(line, e)| Error::Format(format!("line {}: {}", line, e)))
+}
+
+pub fn to_json(entries: &[Entry]) -> String {
+    let mut text = String::from("[\n");
+    for (i, entry) in entries.iter().enumerate() {
+        let separator = if i + 1 < entries.len() { "," } else { "" };
+        let preset = &entry.preset;
+        let name = preset.name().replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(
+            text,
+            "  {{\"slot\": {}, \"frequency\": {}, \"band\": \"{}\", \"pty\": {}, \"name\": \"{}\"}}{}",
+            entry.slot,
+            preset.frequency,
+            preset.band.label(),
+            preset.pty,
+            name,
+            separator
+        )
+        .ok();
+    }
+    text.push_str("]\n");
+    text
+}
+
+/// 每个对象转成一行文本格式再解析，检查规则和 CSV 一致
+pub fn from_json(text: &str) -> Result<Vec<Entry>, Error> {
+    json::objects(text)
+        .map(|object| {
+            // 数字写成字符串也可以
+            let value = |key: &str| match json::field(object, key)? {
+                json::Value::String(text) => Some(text),
+                json::Value::Literal(literal) => Some(literal.to_string()),
+            };
+            let field = |key: &str| {
+                value(key).ok_or_else(|| Error::Format(format!("no {} in `{}`", key, object)))
+            };
+            let line = format!(
+                "{},{},{},{},{}",
+                field("slot")?,
+                field("frequency")?,
+                value("band").unwrap_or_else(|| format::Band::default().label().to_string()),
+                value("pty").unwrap_or_else(|| "0".to_string()),
+                value("name").unwrap_or_default()
+            );
+            Entry::parse(&line)
+                .map_err(|e| Error::Format(format!("{} in `{}`", e, object)))?
+                .ok_or_else(|| Error::Format(format!("empty object `{}`", object)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esp32c3_fm_preset::{Band, Preset};
+
+    /// 名字里有逗号、引号、大括号和反斜杠
+    fn entries() -> Vec<Entry> {
+        ["A,B", "\"Q\" {x}", "}{,\\", " ,\"},"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Entry {
+                slot: i as u8 + 1,
+                preset: Preset {
+                    band: Band::World,
+                    pty: i as u8,
+                    ..Preset::new(88_000 + i as u32 * 100)
+                }
+                .with_name(name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let entries = entries();
+        assert_eq!(from_csv(&to_csv(&entries)).unwrap(), entries);
+        let lines: Vec<String> = to_csv(&entries).lines().map(String::from).collect();
+        assert_eq!(from_export(&lines).unwrap(), entries);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let entries = entries();
+        assert_eq!(from_json(&to_json(&entries)).unwrap(), entries);
+        assert_eq!(from_json("[]\n").unwrap(), []);
+    }
+
+    #[test]
+    fn json_defaults_and_errors() {
+        let text = r#"[{"slot": "2", "frequency": 98500, "name": "POP FM"}]"#;
+        let entry = from_json(text).unwrap()[0];
+        assert_eq!(entry.slot, 2);
+        assert_eq!(entry.preset.band, Band::Standard);
+        assert_eq!(entry.preset.pty, 0);
+        assert_eq!(entry.preset.name(), "POP FM");
+        assert!(from_json(r#"[{"frequency": 98500}]"#).is_err());
+        assert!(from_json(r#"[{"slot": 1, "frequency": 98500, "name": "TOO LONG!"}]"#).is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::raw::{c_char, c_int};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use esp32c3_fm_command::{Command, LineBuffer, HELP, MAX_VOLUME};
+use esp32c3_fm_preset::{self as format, Entry, Preset};
+use esp32c3_fm_telemetry::Record;
+
+use crate::link::{Error, Link};
+use crate::presets::PRESET_COUNT;
+
+/// 模拟的遥测间隔，和固件一致
+const STATUS_PERIOD: Duration = Duration::from_secs(1);
+/// 没有电台时的信号强度
+const NOISE_RSSI: u8 = 12;
+
+struct Station {
+    frequency: u32,
+    rssi: u8,
+    pi: u16,
+    pty: u8,
+    /// 和固件的 `pty_label` 一致
+    label: &'static str,
+    ps: &'static str,
+}
+
+static STATIONS: [Station; 4] = [
+    Station {
+        frequency: 88_100,
+        rssi: 38,
+        pi: 0x1201,
+        pty: 1,
+        label: "News",
+        ps: "NEWS    ",
+    },
+    Station {
+        frequency: 91_500,
+        rssi: 45,
+        pi: 0x1202,
+        pty: 14,
+        label: "Classics",
+        ps: "CLASSIC ",
+    },
+    Station {
+        frequency: 98_500,
+        rssi: 52,
+        pi: 0x1203,
+        pty: 10,
+        label: "Pop M",
+        ps: "POP FM  ",
+    },
+    Station {
+        frequency: 103_900,
+        rssi: 30,
+        pi: 0x1204,
+        pty: 11,
+        label: "Rock M",
+        ps: "ROCK    ",
+    },
+];
+
+/// 模拟固件的串口命令行，没有硬件时用来试用和检查本工具
+///
+/// 模拟的收音机接在一个伪终端上，本工具和连接真的串口一样用 [`Link::open`] 打开。
+/// 命令的解析和固件相同，回显、`ok`/`error:`、各命令的输出以及混在中间的
+/// 日志和遥测数据也和固件一样
+pub fn spawn() -> Result<Link, Error> {
+    let (master, path) = open_pty()?;
+    // stty 打开又关闭从设备时主设备读取会出错，所以先打开连接再开始读取
+    let link = Link::open(&path)?;
+    let reader = master.try_clone()?;
+    let (sender, input) = mpsc::channel();
+    thread::spawn(move || read_bytes(reader, sender));
+    thread::spawn(move || SimRadio::new(master).run(input));
+    Ok(link)
+}
+
+extern "C" {
+    fn grantpt(fd: c_int) -> c_int;
+    fn unlockpt(fd: c_int) -> c_int;
+    fn ptsname(fd: c_int) -> *mut c_char;
+}
+
+/// 打开一对伪终端，返回主设备和从设备的路径
+fn open_pty() -> io::Result<(File, String)> {
+    let master = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/ptmx")?;
+    let fd = master.as_raw_fd();
+    // SAFETY: fd 是刚打开的伪终端主设备，ptsname 返回的字符串在下次调用前有效
+    unsafe {
+        if grantpt(fd) != 0 || unlockpt(fd) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let name = ptsname(fd);
+        if name.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let path = CStr::from_ptr(name).to_string_lossy().into_owned();
+        Ok((master, path))
+    }
+}
+
+/// 从设备关闭后读取会出错，模拟的收音机随之退出
+fn read_bytes(mut reader: File, sender: Sender<Vec<u8>>) {
+    let mut buf = [0; 64];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => {
+                if sender.send(buf[..n].to_vec()).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+struct SimRadio {
+    output: File,
+    started: Instant,
+    frequency: u32,
+    volume: u8,
+    presets: [Preset; PRESET_COUNT as usize],
+    telemetry: bool,
+}
+
+impl SimRadio {
+    fn new(output: File) -> Self {
+        SimRadio {
+            output,
+            started: Instant::now(),
+            frequency: 87_500,
+            volume: 8,
+            presets: [Preset::EMPTY; PRESET_COUNT as usize],
+            telemetry: false,
+        }
+    }
+
+    fn run(mut self, input: Receiver<Vec<u8>>) {
+        let mut line = LineBuffer::new();
+        let mut previous = 0;
+        let mut last_report = Instant::now();
+        loop {
+            let timeout = STATUS_PERIOD.saturating_sub(last_report.elapsed());
+            match input.recv_timeout(timeout) {
+                Ok(bytes) => {
+                    for byte in bytes {
+                        // 和固件一样回车换行只算一次
+                        let crlf = previous == b'\r' && byte == b'\n';
+                        previous = byte;
+                        if crlf {
+                            continue;
+                        }
+                        self.echo(byte);
+                        match line.push(byte) {
+                            Some(Ok(command)) => self.handle(command),
+                            Some(Err(e)) => self.print(format!("error: {}", e)),
+                            None => {}
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            if last_report.elapsed() >= STATUS_PERIOD {
+                last_report = Instant::now();
+                self.report_status();
+            }
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.output.write_all(bytes).ok();
+    }
+
+    fn echo(&mut self, byte: u8) {
+        match byte {
+            b'\r' | b'\n' => self.write(b"\r\n"),
+            0x08 | 0x7F => self.write(b"\x08 \x08"),
+            _ if byte.is_ascii() => self.write(&[byte]),
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, line: impl AsRef<str>) {
+        let line = format!("{}\r\n", line.as_ref());
+        self.write(line.as_bytes());
+    }
+
+    /// 和固件 `log` 的输出格式相同
+    fn log(&mut self, message: &str) {
+        self.print(format!("\x1b[32mINFO - {}\x1b[0m", message));
+    }
+
+    fn station(&self) -> Option<&'static Station> {
+        STATIONS.iter().find(|s| s.frequency == self.frequency)
+    }
+
+    fn rssi(&self) -> u8 {
+        self.station().map_or(NOISE_RSSI, |s| s.rssi)
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Help => {
+                for line in HELP.lines() {
+                    self.print(line);
+                }
+            }
+            Command::Tune(freq) => self.tune(freq),
+            Command::Seek(up) => self.seek(up),
+            Command::Volume(volume) => self.volume = volume.min(MAX_VOLUME),
+            Command::PresetSave(n) => {
+                self.presets[n as usize] = match self.station() {
+                    Some(s) => Preset {
+                        pty: s.pty,
+                        ..Preset::new(self.frequency)
+                    }
+                    .with_name(s.ps),
+                    None => Preset::new(self.frequency),
+                };
+                self.log(&format!("save preset {}", n + 1));
+            }
+            Command::PresetRecall(n) => match self.presets[n as usize] {
+                preset if preset.is_empty() => self.print(format!("preset {} is empty", n + 1)),
+                preset => self.tune(preset.frequency),
+            },
+            Command::PresetList => {
+                for (n, preset) in self.presets.into_iter().enumerate() {
+                    match preset.frequency {
+                        0 => self.print(format!("{}: -", n + 1)),
+                        freq => self.print(format!("{}: {} kHz {}", n + 1, freq, preset.name())),
+                    }
+                }
+            }
+            Command::PresetExport => {
+                self.print(format::HEADER);
+                for (n, preset) in self.presets.into_iter().enumerate() {
+                    if !preset.is_empty() {
+                        let slot = n as u8 + 1;
+                        self.print(Entry { slot, preset }.to_string());
+                    }
+                }
+            }
+            Command::PresetImport(n, preset) => {
+                self.presets[n as usize] = preset;
+                self.print(format!("import preset {} success!", n + 1));
+            }
+            Command::RdsDump => match self.station() {
+                Some(s) => self.print(format!(
+                    "pi: Some({}), pty: {} {}, ps: Some({:?})",
+                    s.pi, s.pty, s.label, s.ps
+                )),
+                None => self.print("pi: None, pty: 0 None, ps: None"),
+            },
+            Command::Status => {
+                let line = format!(
+                    "freq: {} kHz, rssi: {}, stereo: {}, volume: {}, muted: false, seek threshold: 8",
+                    self.frequency,
+                    self.rssi(),
+                    self.station().is_some(),
+                    self.volume
+                );
+                self.print(line);
+            }
+            Command::Telemetry(on) => {
+                self.telemetry = on;
+                self.report(Record::Hello);
+            }
+            Command::Standby => self.print("standby"),
+            Command::Reboot => self.print("reboot"),
+        }
+        self.print("ok");
+    }
+
+    fn tune(&mut self, freq: u32) {
+        self.frequency = freq;
+        self.report_rds();
+    }
+
+    /// 搜台比较慢，期间会输出遥测数据
+    fn seek(&mut self, up: bool) {
+        let next = if up {
+            STATIONS
+                .iter()
+                .find(|s| s.frequency > self.frequency)
+                .or(STATIONS.first())
+        } else {
+            STATIONS
+                .iter()
+                .rev()
+                .find(|s| s.frequency < self.frequency)
+                .or(STATIONS.last())
+        };
+        self.report_status();
+        if let Some(station) = next {
+            self.frequency = station.frequency;
+        }
+        self.report_rds();
+    }
+
+    /// 输出一条遥测数据，编码和固件相同
+    fn report(&mut self, record: Record) {
+        if self.telemetry {
+            let mut line = String::new();
+            record
+                .write(self.started.elapsed().as_millis() as u64, &mut line)
+                .ok();
+            self.print(line);
+        }
+    }
+
+    fn report_status(&mut self) {
+        self.report(Record::Status {
+            frequency: self.frequency,
+            rssi: self.rssi(),
+            stereo: self.station().is_some(),
+            volume: self.volume,
+            muted: false,
+        });
+    }
+
+    fn report_rds(&mut self) {
+        if let Some(s) = self.station() {
+            self.report(Record::Rds {
+                pi: s.pi,
+                pty: s.pty,
+                program_service: Some(s.ps),
+            });
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use crate::json;
+
+/// 能解析的遥测格式版本
+pub use esp32c3_fm_telemetry::SCHEMA_VERSION;
+
+/// 固件输出的一条遥测数据，格式见 `telemetry/src/lib.rs`
+#[derive(PartialEq, Debug, Clone)]
+pub enum Record {
+    Hello {
+        version: u8,
+    },
+    Status {
+        /// 频率(kHz)
+        frequency: u32,
+        rssi: u8,
+        stereo: bool,
+        volume: u8,
+        muted: bool,
+    },
+    Rds {
+        pi: u16,
+        pty: u8,
+        program_service: Option<String>,
+    },
+    Input {
+        source: String,
+        kind: String,
+        velocity: f32,
+    },
+}
+
+/// 解析一行遥测数据，返回开机后的毫秒数和记录。
+/// 不是 `{` 开头的行、未知的类型或缺少字段时返回 None
+pub fn parse(line: &str) -> Option<(u64, Record)> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    let ms = value(line, "ms")?;
+    let record = match json::string_field(line, "t")?.as_str() {
+        "hello" => Record::Hello {
+            version: value(line, "v")?,
+        },
+        "status" => Record::Status {
+            frequency: value(line, "freq")?,
+            rssi: value(line, "rssi")?,
+            stereo: value(line, "stereo")?,
+            volume: value(line, "volume")?,
+            muted: value(line, "muted")?,
+        },
+        "rds" => Record::Rds {
+            pi: value(line, "pi")?,
+            pty: value(line, "pty")?,
+            program_service: match json::field(line, "ps")? {
+                json::Value::String(ps) => Some(ps),
+                json::Value::Literal("null") => None,
+                json::Value::Literal(_) => return None,
+            },
+        },
+        "input" => Record::Input {
+            source: json::string_field(line, "source")?,
+            kind: json::string_field(line, "kind")?,
+            velocity: value(line, "velocity")?,
+        },
+        _ => return None,
+    };
+    Some((ms, record))
+}
+
+/// 数字和 true/false
+fn value<T: std::str::FromStr>(line: &str, key: &str) -> Option<T> {
+    json::literal_field(line, key)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esp32c3_fm_telemetry::Record as Encoded;
+
+    // 和固件文档里的示例一致
+    const HELLO: &str = r#"{"t":"hello","ms":0,"v":1}"#;
+    const STATUS: &str =
+        r#"{"t":"status","ms":1000,"freq":98500,"rssi":42,"stereo":true,"volume":8,"muted":false}"#;
+    const RDS: &str = r#"{"t":"rds","ms":1200,"pi":4660,"pty":10,"ps":"RADIO 1 "}"#;
+    const INPUT: &str =
+        r#"{"t":"input","ms":1500,"source":"Ec11","kind":"EC11Front","velocity":3.5}"#;
+
+    #[test]
+    fn hello() {
+        assert_eq!(
+            parse(HELLO),
+            Some((
+                0,
+                Record::Hello {
+                    version: SCHEMA_VERSION
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            parse(STATUS),
+            Some((
+                1000,
+                Record::Status {
+                    frequency: 98_500,
+                    rssi: 42,
+                    stereo: true,
+                    volume: 8,
+                    muted: false,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn rds() {
+        assert_eq!(
+            parse(RDS),
+            Some((
+                1200,
+                Record::Rds {
+                    pi: 0x1234,
+                    pty: 10,
+                    program_service: Some("RADIO 1 ".to_string()),
+                }
+            ))
+        );
+        let line = r#"{"t":"rds","ms":1300,"pi":4660,"pty":0,"ps":null}"#;
+        assert_eq!(
+            parse(line),
+            Some((
+                1300,
+                Record::Rds {
+                    pi: 0x1234,
+                    pty: 0,
+                    program_service: None,
+                }
+            ))
+        );
+        // 节目名里有引号、大括号或者就是 null
+        let line = r#"{"t":"rds","ms":1400,"pi":4660,"pty":0,"ps":"\"A\",}{"}"#;
+        assert_eq!(
+            parse(line).map(|(_, record)| record),
+            Some(Record::Rds {
+                pi: 0x1234,
+                pty: 0,
+                program_service: Some("\"A\",}{".to_string()),
+            })
+        );
+        let line = r#"{"t":"rds","ms":1400,"pi":4660,"pty":0,"ps":"null"}"#;
+        assert!(matches!(
+            parse(line),
+            Some((_, Record::Rds { program_service: Some(ps), .. })) if ps == "null"
+        ));
+    }
+
+    #[test]
+    fn input() {
+        assert_eq!(
+            parse(INPUT),
+            Some((
+                1500,
+                Record::Input {
+                    source: "Ec11".to_string(),
+                    kind: "EC11Front".to_string(),
+                    velocity: 3.5,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn not_telemetry() {
+        assert_eq!(parse("freq: 98500 kHz, rssi: 42"), None);
+        assert_eq!(parse("\x1b[32mINFO - start\x1b[0m"), None);
+        assert_eq!(parse(r#"{"t":"unknown","ms":0}"#), None);
+        // 缺少字段或类型不对
+        assert_eq!(parse(r#"{"t":"hello","ms":0}"#), None);
+        assert_eq!(parse(r#"{"t":"status","ms":1000,"freq":98500}"#), None);
+        assert_eq!(
+            parse(
+                r#"{"t":"status","ms":1000,"freq":98500,"rssi":42,"stereo":1,"volume":8,"muted":false}"#
+            ),
+            None
+        );
+    }
+
+    /// 用固件的编码写出再解析，两边的格式不会不一致
+    fn round_trip(record: Encoded, uptime_ms: u64) -> Option<(u64, Record)> {
+        let mut line = String::new();
+        record.write(uptime_ms, &mut line).unwrap();
+        parse(&line)
+    }
+
+    #[test]
+    fn round_trip_hello() {
+        assert_eq!(
+            round_trip(Encoded::Hello, 7),
+            Some((
+                7,
+                Record::Hello {
+                    version: SCHEMA_VERSION
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn round_trip_status() {
+        let encoded = Encoded::Status {
+            frequency: 108_000,
+            rssi: 127,
+            stereo: false,
+            volume: 15,
+            muted: true,
+        };
+        assert_eq!(
+            round_trip(encoded, u64::MAX),
+            Some((
+                u64::MAX,
+                Record::Status {
+                    frequency: 108_000,
+                    rssi: 127,
+                    stereo: false,
+                    volume: 15,
+                    muted: true,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn round_trip_rds() {
+        for ps in [
+            None,
+            Some("RADIO 1 "),
+            Some("null"),
+            Some("\"A\\\",}{"),
+            Some("\t\u{7}"),
+        ] {
+            let encoded = Encoded::Rds {
+                pi: 0xFFFF,
+                pty: 31,
+                program_service: ps,
+            };
+            assert_eq!(
+                round_trip(encoded, 1200),
+                Some((
+                    1200,
+                    Record::Rds {
+                        pi: 0xFFFF,
+                        pty: 31,
+                        program_service: ps.map(String::from),
+                    }
+                )),
+                "{:?}",
+                ps
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_input() {
+        let encoded = Encoded::Input {
+            source: "Sw2",
+            kind: "KeyLongStart",
+            velocity: -2.5,
+        };
+        assert_eq!(
+            round_trip(encoded, 1500),
+            Some((
+                1500,
+                Record::Input {
+                    source: "Sw2".to_string(),
+                    kind: "KeyLongStart".to_string(),
+                    velocity: -2.5,
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,302 @@
+#![no_std]
+
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+use core::fmt;
+
+/// 收音机保存的预设电台数量
+pub const PRESET_COUNT: u8 = 8;
+/// 电台名最多 8 个字符，和 RDS 节目名一致
+pub const NAME_LEN: usize = 8;
+/// 文本格式的第一行
+pub const HEADER: &str = "slot,frequency,band,pty,name";
+/// RDS 节目类型 0～31
+pub const MAX_PTY: u8 = 31;
+
+/// RDA5807M 支持的波段
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+pub enum Band {
+    /// 87～108MHz
+    #[default]
+    Standard,
+    /// 76～91MHz
+    Japan,
+    /// 76～108MHz
+    World,
+    /// 65～76MHz
+    EastEurope,
+}
+
+impl Band {
+    pub const ALL: [Band; 4] = [Band::Standard, Band::Japan, Band::World, Band::EastEurope];
+
+    /// 频率范围(kHz)
+    pub fn range(&self) -> (u32, u32) {
+        match self {
+            Band::Standard => (87_000, 108_000),
+            Band::Japan => (76_000, 91_000),
+            Band::World => (76_000, 108_000),
+            Band::EastEurope => (65_000, 76_000),
+        }
+    }
+
+    pub fn contains(&self, frequency: u32) -> bool {
+        let (min, max) = self.range();
+        (min..=max).contains(&frequency)
+    }
+
+    /// 文本格式里的写法
+    pub fn label(&self) -> &'static str {
+        match self {
+            Band::Standard => "87-108",
+            Band::Japan => "76-91",
+            Band::World => "76-108",
+            Band::EastEurope => "65-76",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Band> {
+        Band::ALL.into_iter().find(|band| band.label() == label)
+    }
+
+    pub fn from_u8(value: u8) -> Option<Band> {
+        Band::ALL.get(value as usize).copied()
+    }
+}
+
+/// 一个预设电台
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Preset {
+    /// 频率(kHz)，0 表示未保存
+    pub frequency: u32,
+    pub band: Band,
+    /// RDS 节目类型，没有时为 0
+    pub pty: u8,
+    /// 可显示的 ASCII 字符，不足的部分用空格填充
+    pub name: [u8; NAME_LEN],
+}
+
+impl Preset {
+    pub const EMPTY: Preset = Preset::new(0);
+
+    pub const fn new(frequency: u32) -> Self {
+        Preset {
+            frequency,
+            band: Band::Standard,
+            pty: 0,
+            name: [b' '; NAME_LEN],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frequency == 0
+    }
+
+    /// 去掉末尾空格的电台名
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name)
+            .unwrap_or("")
+            .trim_end_matches(' ')
+    }
+
+    /// 设置电台名，超长的部分截掉，不可显示的字符换成 `?`
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = [b' '; NAME_LEN];
+        for (c, byte) in self.name.iter_mut().zip(name.trim_end().bytes()) {
+            *c = if is_name_char(byte) { byte } else { b'?' };
+        }
+        self
+    }
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Preset::EMPTY
+    }
+}
+
+fn is_name_char(byte: u8) -> bool {
+    (0x20..0x7F).contains(&byte)
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum FormatError {
+    /// 列数不对
+    Columns,
+    Slot,
+    /// 不是数字或不在波段范围内
+    Frequency,
+    Band,
+    Pty,
+    /// 超过 8 个字符或有不可显示的字符
+    Name,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            FormatError::Columns => "expected slot,frequency,band,pty,name",
+            FormatError::Slot => "invalid slot",
+            FormatError::Frequency => "invalid frequency",
+            FormatError::Band => "invalid band",
+            FormatError::Pty => "invalid pty",
+            FormatError::Name => "invalid name",
+        };
+        f.write_str(text)
+    }
+}
+
+/// 文本格式中的一行，例如 `1,98500,87-108,10,POP FM`
+///
+/// 电台名在最后一列，可以包含逗号
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Entry {
+    /// 编号从 1 开始
+    pub slot: u8,
+    pub preset: Preset,
+}
+
+impl Entry {
+    /// 空行、注释(`#` 开头)和标题行返回 `Ok(None)`
+    pub fn parse(line: &str) -> Result<Option<Entry>, FormatError> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() || line.starts_with('#') || line == HEADER {
+            return Ok(None);
+        }
+        let mut columns = line.splitn(5, ',');
+        let mut next = || columns.next().ok_or(FormatError::Columns);
+        let slot = next()?.trim().parse().map_err(|_| FormatError::Slot)?;
+        let frequency = next()?.trim().parse().map_err(|_| FormatError::Frequency)?;
+        let band = Band::from_label(next()?.trim()).ok_or(FormatError::Band)?;
+        let pty = next()?.trim().parse().map_err(|_| FormatError::Pty)?;
+        let name = next()?;
+        if slot == 0 {
+            return Err(FormatError::Slot);
+        }
+        if !band.contains(frequency) {
+            return Err(FormatError::Frequency);
+        }
+        if pty > MAX_PTY {
+            return Err(FormatError::Pty);
+        }
+        if name.len() > NAME_LEN || !name.bytes().all(is_name_char) {
+            return Err(FormatError::Name);
+        }
+        let preset = Preset {
+            frequency,
+            band,
+            pty,
+            ..Preset::EMPTY
+        };
+        Ok(Some(Entry {
+            slot,
+            preset: preset.with_name(name),
+        }))
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{}",
+            self.slot,
+            self.preset.frequency,
+            self.preset.band.label(),
+            self.preset.pty,
+            self.preset.name()
+        )
+    }
+}
+
+/// 整个文件，第一行是 [`HEADER`]
+#[cfg(any(feature = "std", test))]
+pub fn to_text(entries: &[Entry]) -> std::string::String {
+    use std::fmt::Write;
+
+    let mut text = std::string::String::from(HEADER);
+    text.push('\n');
+    for entry in entries {
+        writeln!(text, "{}", entry).ok();
+    }
+    text
+}
+
+/// 出错时返回行号(从 1 开始)
+#[cfg(any(feature = "std", test))]
+pub fn from_text(text: &str) -> Result<std::vec::Vec<Entry>, (usize, FormatError)> {
+    let mut entries = std::vec::Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if let Some(entry) = Entry::parse(line).map_err(|e| (n + 1, e))? {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    fn entry(slot: u8, name: &str) -> Entry {
+        Entry {
+            slot,
+            preset: Preset {
+                pty: 10,
+                ..Preset::new(98_500)
+            }
+            .with_name(name),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Entry::parse("1,98500,87-108,10,POP FM\r\n"),
+            Ok(Some(entry(1, "POP FM")))
+        );
+        assert_eq!(Entry::parse(HEADER), Ok(None));
+        assert_eq!(Entry::parse("# comment"), Ok(None));
+        assert_eq!(Entry::parse("  "), Ok(None));
+        assert_eq!(Entry::parse("1,98500,87-108,10"), Err(FormatError::Columns));
+        assert_eq!(Entry::parse("0,98500,87-108,10,"), Err(FormatError::Slot));
+        assert_eq!(
+            Entry::parse("1,76000,87-108,10,"),
+            Err(FormatError::Frequency)
+        );
+        assert_eq!(Entry::parse("1,98500,88-108,10,"), Err(FormatError::Band));
+        assert_eq!(Entry::parse("1,98500,87-108,32,"), Err(FormatError::Pty));
+        assert_eq!(
+            Entry::parse("1,98500,87-108,10,TOO LONG!"),
+            Err(FormatError::Name)
+        );
+        assert_eq!(Entry::parse("1,98500,87-108,10,\t"), Err(FormatError::Name));
+    }
+
+    /// 名字在最后一列，逗号、引号和大括号不需要转义
+    #[test]
+    fn round_trip() {
+        for name in ["A,B", "\"Q\" {x}", "}{,\\", ",,,,", " {\"a\"}", "#1", ""] {
+            let entry = entry(3, name);
+            assert_eq!(Entry::parse(&entry.to_string()), Ok(Some(entry)));
+        }
+        let entries = [entry(1, "A,B"), entry(2, "\"Q\""), entry(8, "{}")];
+        assert_eq!(from_text(&to_text(&entries)).unwrap(), entries);
+    }
+
+    #[test]
+    fn text_errors() {
+        let text = "slot,frequency,band,pty,name\n1,98500,87-108,10,A\n2,1,87-108,0,B\n";
+        assert_eq!(from_text(text), Err((3, FormatError::Frequency)));
+    }
+
+    #[test]
+    fn name() {
+        let preset = Preset::EMPTY.with_name("ABCDEFGHIJ");
+        assert_eq!(preset.name(), "ABCDEFGH");
+        assert_eq!(Preset::EMPTY.with_name("é").name(), "??");
+        assert_eq!(Preset::EMPTY.with_name("A  ").name(), "A");
+    }
+}
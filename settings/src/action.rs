@@ -0,0 +1,209 @@
+use crate::event::EventType;
+use crate::input::{InputEvent, InputId};
+
+pub use esp32c3_fm_preset::PRESET_COUNT;
+
+/// 按键映射后执行的动作
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Action {
+    None,
+    TuneUp,
+    TuneDown,
+    SeekUp,
+    SeekDown,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    SeekThresholdUp,
+    SeekThresholdDown,
+    PresetRecall(u8),
+    PresetSave(u8),
+    Refresh,
+    OpenMenu,
+    /// 关闭收音机进入深度睡眠
+    Standby,
+}
+
+impl Action {
+    /// 设置菜单里编辑时的切换顺序，预设按编号展开
+    pub fn next(&self) -> Action {
+        match *self {
+            Action::None => Action::TuneUp,
+            Action::TuneUp => Action::TuneDown,
+            Action::TuneDown => Action::SeekUp,
+            Action::SeekUp => Action::SeekDown,
+            Action::SeekDown => Action::VolumeUp,
+            Action::VolumeUp => Action::VolumeDown,
+            Action::VolumeDown => Action::ToggleMute,
+            Action::ToggleMute => Action::SeekThresholdUp,
+            Action::SeekThresholdUp => Action::SeekThresholdDown,
+            Action::SeekThresholdDown => Action::PresetRecall(0),
+            Action::PresetRecall(n) if n + 1 < PRESET_COUNT => Action::PresetRecall(n + 1),
+            Action::PresetRecall(_) => Action::PresetSave(0),
+            Action::PresetSave(n) if n + 1 < PRESET_COUNT => Action::PresetSave(n + 1),
+            Action::PresetSave(_) => Action::Refresh,
+            Action::Refresh => Action::OpenMenu,
+            Action::OpenMenu => Action::Standby,
+            Action::Standby => Action::None,
+        }
+    }
+
+    pub fn prev(&self) -> Action {
+        match *self {
+            Action::None => Action::Standby,
+            Action::TuneUp => Action::None,
+            Action::TuneDown => Action::TuneUp,
+            Action::SeekUp => Action::TuneDown,
+            Action::SeekDown => Action::SeekUp,
+            Action::VolumeUp => Action::SeekDown,
+            Action::VolumeDown => Action::VolumeUp,
+            Action::ToggleMute => Action::VolumeDown,
+            Action::SeekThresholdUp => Action::ToggleMute,
+            Action::SeekThresholdDown => Action::SeekThresholdUp,
+            Action::PresetRecall(0) => Action::SeekThresholdDown,
+            Action::PresetRecall(n) => Action::PresetRecall(n - 1),
+            Action::PresetSave(0) => Action::PresetRecall(PRESET_COUNT - 1),
+            Action::PresetSave(n) => Action::PresetSave(n - 1),
+            Action::Refresh => Action::PresetSave(PRESET_COUNT - 1),
+            Action::OpenMenu => Action::Refresh,
+            Action::Standby => Action::OpenMenu,
+        }
+    }
+
+    /// 保存到 flash 时的编码 `(类型, 参数)`
+    pub fn to_bytes(&self) -> [u8; 2] {
+        match *self {
+            Action::None => [0, 0],
+            Action::TuneUp => [1, 0],
+            Action::TuneDown => [2, 0],
+            Action::SeekUp => [3, 0],
+            Action::SeekDown => [4, 0],
+            Action::VolumeUp => [5, 0],
+            Action::VolumeDown => [6, 0],
+            Action::ToggleMute => [7, 0],
+            Action::SeekThresholdUp => [8, 0],
+            Action::SeekThresholdDown => [9, 0],
+            Action::PresetRecall(n) => [10, n],
+            Action::PresetSave(n) => [11, n],
+            Action::Refresh => [12, 0],
+            Action::OpenMenu => [13, 0],
+            Action::Standby => [14, 0],
+        }
+    }
+
+    pub fn from_bytes(bytes: [u8; 2]) -> Option<Action> {
+        let action = match bytes {
+            [0, _] => Action::None,
+            [1, _] => Action::TuneUp,
+            [2, _] => Action::TuneDown,
+            [3, _] => Action::SeekUp,
+            [4, _] => Action::SeekDown,
+            [5, _] => Action::VolumeUp,
+            [6, _] => Action::VolumeDown,
+            [7, _] => Action::ToggleMute,
+            [8, _] => Action::SeekThresholdUp,
+            [9, _] => Action::SeekThresholdDown,
+            [10, n] if n < PRESET_COUNT => Action::PresetRecall(n),
+            [11, n] if n < PRESET_COUNT => Action::PresetSave(n),
+            [12, _] => Action::Refresh,
+            [13, _] => Action::OpenMenu,
+            [14, _] => Action::Standby,
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+/// 一个输入事件到动作的绑定
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Binding {
+    pub source: InputId,
+    pub kind: EventType,
+    pub action: Action,
+}
+
+impl Binding {
+    const fn new(source: InputId, kind: EventType, action: Action) -> Self {
+        Binding {
+            source,
+            kind,
+            action,
+        }
+    }
+
+    /// 旋钮长按固定打开菜单，不能修改，避免改掉后再也进不了菜单
+    pub fn is_fixed(&self) -> bool {
+        self.source == InputId::Ec11 && self.kind == EventType::KeyLongStart
+    }
+}
+
+/// 可绑定的事件数：三个按键的短按和长按，旋钮的短按、长按和四种转动
+pub const KEYMAP_LEN: usize = 12;
+
+/// 按键映射表，绑定的事件固定，动作可以按板子或运行时修改
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Keymap {
+    pub bindings: [Binding; KEYMAP_LEN],
+}
+
+impl Keymap {
+    /// 默认映射，两个版本的板子按键和旋钮相同，共用这一份
+    pub const fn standard() -> Self {
+        use EventType::*;
+        use InputId::*;
+        Keymap {
+            bindings: [
+                Binding::new(Sw1, KeyShort, Action::SeekUp),
+                Binding::new(Sw1, KeyLongStart, Action::SeekDown),
+                Binding::new(Sw2, KeyShort, Action::SeekThresholdDown),
+                Binding::new(Sw2, KeyLongStart, Action::SeekThresholdUp),
+                Binding::new(Sw3, KeyShort, Action::VolumeDown),
+                Binding::new(Sw3, KeyLongStart, Action::ToggleMute),
+                Binding::new(Ec11, KeyShort, Action::Refresh),
+                Binding::new(Ec11, KeyLongStart, Action::OpenMenu),
+                Binding::new(Ec11, EC11Front, Action::TuneUp),
+                Binding::new(Ec11, EC11Back, Action::TuneDown),
+                Binding::new(Ec11, EC11PressedFront, Action::VolumeUp),
+                Binding::new(Ec11, EC11PressedBack, Action::VolumeDown),
+            ],
+        }
+    }
+
+    pub fn lookup(&self, event: &InputEvent) -> Action {
+        self.bindings
+            .iter()
+            .find(|binding| binding.source == event.source && binding.kind == event.kind)
+            .map(|binding| binding.action)
+            .unwrap_or(Action::None)
+    }
+
+    /// 固定的绑定不会被修改
+    pub fn set(&mut self, source: InputId, kind: EventType, action: Action) {
+        if let Some(binding) = self
+            .bindings
+            .iter_mut()
+            .find(|binding| binding.source == source && binding.kind == kind)
+        {
+            if !binding.is_fixed() {
+                binding.action = action;
+            }
+        }
+    }
+
+    /// 恢复固定的绑定，之前保存的设置里可能已经被改掉
+    pub fn restore_fixed(&mut self) {
+        for binding in self
+            .bindings
+            .iter_mut()
+            .filter(|binding| binding.is_fixed())
+        {
+            binding.action = Action::OpenMenu;
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::standard()
+    }
+}
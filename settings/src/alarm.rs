@@ -0,0 +1,314 @@
+use embassy_time::Duration;
+
+use crate::action::PRESET_COUNT;
+use crate::tuner::MAX_VOLUME;
+
+/// 闹钟的数量
+pub const ALARM_COUNT: usize = 2;
+/// 贪睡后再次响铃的间隔
+pub const SNOOZE: Duration = Duration::from_secs(9 * 60);
+/// 渐强时间的上限(分钟)
+pub const MAX_RAMP: u8 = 30;
+/// 自动关闭时间的上限(分钟)
+pub const MAX_DURATION: u8 = 120;
+
+const MINUTE_MS: u32 = 60 * 1000;
+const DAY_MS: u32 = 24 * 60 * MINUTE_MS;
+/// 一周的毫秒数，星期时钟在这个范围内循环
+pub const WEEK_MS: u32 = 7 * DAY_MS;
+
+/// 菜单里可选的重复方式，每天一位，bit0 为星期一
+pub const DAY_PATTERNS: [u8; 10] = [
+    0b111_1111, 0b001_1111, 0b110_0000, 0b000_0001, 0b000_0010, 0b000_0100, 0b000_1000, 0b001_0000,
+    0b010_0000, 0b100_0000,
+];
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// 一个闹钟，到时间后切换到预设电台并逐渐调大音量
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Alarm {
+    pub enabled: bool,
+    pub hour: u8,
+    pub minute: u8,
+    /// 每天一位，bit0 为星期一
+    pub days: u8,
+    /// 预设编号，从 0 开始，预设为空时使用当前频率
+    pub preset: u8,
+    /// 开始响铃时的音量
+    pub volume: u8,
+    /// 从开始音量升到设置音量的时间(分钟)，0 表示不渐强
+    pub ramp: u8,
+    /// 没有按键时多久后自动关闭(分钟)
+    pub duration: u8,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Alarm {
+            enabled: false,
+            hour: 7,
+            minute: 0,
+            days: DAY_PATTERNS[1],
+            preset: 0,
+            volume: 2,
+            ramp: 5,
+            duration: 30,
+        }
+    }
+}
+
+impl Alarm {
+    pub const ENCODED_LEN: usize = 8;
+
+    pub fn to_bytes(&self) -> [u8; Self::ENCODED_LEN] {
+        [
+            self.enabled as u8,
+            self.hour,
+            self.minute,
+            self.days,
+            self.preset,
+            self.volume,
+            self.ramp,
+            self.duration,
+        ]
+    }
+
+    /// 数值超出范围时返回 `None`
+    pub fn from_bytes(bytes: [u8; Self::ENCODED_LEN]) -> Option<Alarm> {
+        let [enabled, hour, minute, days, preset, volume, ramp, duration] = bytes;
+        let valid = hour < 24
+            && minute < 60
+            && days < 0x80
+            && preset < PRESET_COUNT
+            && volume <= MAX_VOLUME
+            && ramp <= MAX_RAMP
+            && (1..=MAX_DURATION).contains(&duration);
+        valid.then_some(Alarm {
+            enabled: enabled != 0,
+            hour,
+            minute,
+            days,
+            preset,
+            volume,
+            ramp,
+            duration,
+        })
+    }
+
+    pub fn rings_on(&self, weekday: u8) -> bool {
+        self.days & (1 << weekday) != 0
+    }
+
+    /// 从 `now`(星期时钟的毫秒数)到下次响铃的毫秒数，不会返回 0
+    pub fn next(&self, now: u32) -> Option<u32> {
+        if !self.enabled {
+            return None;
+        }
+        let time = (self.hour as u32 * 60 + self.minute as u32) * MINUTE_MS;
+        let today = now / DAY_MS;
+        // 多看一天，今天已经过了的时间下周再响
+        (0..=7)
+            .map(|day| today + day)
+            .filter(|day| self.rings_on((day % 7) as u8))
+            .map(|day| day * DAY_MS + time)
+            .find(|at| *at > now)
+            .map(|at| at - now)
+    }
+
+    /// 响铃 `elapsed` 后的音量，从开始音量线性变化到 `volume`
+    pub fn volume(&self, volume: u8, elapsed: Duration) -> u8 {
+        let ramp = Duration::from_secs(self.ramp as u64 * 60);
+        if elapsed >= ramp {
+            return volume;
+        }
+        let start = self.volume as i64;
+        let delta = (volume as i64 - start) * elapsed.as_millis() as i64 / ramp.as_millis() as i64;
+        (start + delta) as u8
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration as u64 * 60)
+    }
+
+    /// 菜单中显示的重复方式
+    pub fn days_label(&self) -> &'static str {
+        match self.days {
+            0b111_1111 => "Daily",
+            0b001_1111 => "Mon-Fri",
+            0b110_0000 => "Sat-Sun",
+            days if days.count_ones() == 1 => DAY_NAMES[days.trailing_zeros() as usize],
+            _ => "Custom",
+        }
+    }
+
+    /// 在 [`DAY_PATTERNS`] 中前后切换
+    pub fn cycle_days(&mut self, forward: bool) {
+        let len = DAY_PATTERNS.len();
+        let index = DAY_PATTERNS.iter().position(|days| *days == self.days);
+        let next = match (index, forward) {
+            (Some(index), true) => (index + 1) % len,
+            (Some(index), false) => (index + len - 1) % len,
+            (None, _) => 0,
+        };
+        self.days = DAY_PATTERNS[next];
+    }
+}
+
+/// 下一个响铃的闹钟和等待的毫秒数
+pub fn next_alarm(alarms: &[Alarm], now: u32) -> Option<(usize, u32)> {
+    alarms
+        .iter()
+        .enumerate()
+        .filter_map(|(index, alarm)| Some((index, alarm.next(now)?)))
+        .min_by_key(|(_, wait)| *wait)
+}
+
+/// 从 `since` 开始经过 `elapsed` 毫秒期间到时间的闹钟
+pub fn due(alarms: &[Alarm], since: u32, elapsed: u32) -> Option<usize> {
+    next_alarm(alarms, since)
+        .filter(|(_, wait)| *wait <= elapsed)
+        .map(|(index, _)| index)
+}
+
+/// 星期时钟，从星期一 0 点开始的毫秒数
+///
+/// 以 RTC 时间为基准，深度睡眠期间继续走。RTC 慢时钟误差较大，收到 RDS 时间后重新校准
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Clock {
+    rtc_ms: u64,
+    week_ms: u32,
+}
+
+impl Clock {
+    /// `rtc_ms` 时刻是星期 `weekday`(0 为星期一)的第 `minutes` 分钟
+    pub fn new(rtc_ms: u64, weekday: u8, minutes: u16) -> Clock {
+        Clock {
+            rtc_ms,
+            week_ms: weekday as u32 % 7 * DAY_MS + minutes as u32 * MINUTE_MS,
+        }
+    }
+
+    pub fn now(&self, rtc_ms: u64) -> u32 {
+        let elapsed = rtc_ms.saturating_sub(self.rtc_ms);
+        ((self.week_ms as u64 + elapsed) % WEEK_MS as u64) as u32
+    }
+
+    /// 本地时间 `(时, 分)`
+    pub fn time_of_day(&self, rtc_ms: u64) -> (u8, u8) {
+        let minutes = self.now(rtc_ms) % DAY_MS / MINUTE_MS;
+        ((minutes / 60) as u8, (minutes % 60) as u8)
+    }
+
+    /// 保存到 RTC 内存用
+    pub fn to_parts(&self) -> (u64, u32) {
+        (self.rtc_ms, self.week_ms)
+    }
+
+    pub fn from_parts(rtc_ms: u64, week_ms: u32) -> Option<Clock> {
+        (week_ms < WEEK_MS).then_some(Clock { rtc_ms, week_ms })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: u32 = 60 * MINUTE_MS;
+    const MONDAY: u8 = 0b000_0001;
+    const SUNDAY: u8 = 0b100_0000;
+
+    fn alarm(hour: u8, minute: u8, days: u8) -> Alarm {
+        Alarm {
+            enabled: true,
+            hour,
+            minute,
+            days,
+            ..Alarm::default()
+        }
+    }
+
+    /// 星期 `weekday`(0 为星期一)的 `hour:minute`
+    fn at(weekday: u32, hour: u32, minute: u32) -> u32 {
+        weekday * DAY_MS + hour * HOUR_MS + minute * MINUTE_MS
+    }
+
+    #[test]
+    fn next_same_day() {
+        let alarm = alarm(7, 30, DAY_PATTERNS[0]);
+        assert_eq!(alarm.next(at(2, 6, 0)), Some(90 * MINUTE_MS));
+        // 正好到时间时等下一天，不返回 0
+        assert_eq!(alarm.next(at(2, 7, 30)), Some(DAY_MS));
+        let disabled = Alarm {
+            enabled: false,
+            ..alarm
+        };
+        assert_eq!(disabled.next(at(2, 6, 0)), None);
+        assert_eq!(Alarm::default().next(0), None);
+    }
+
+    #[test]
+    fn next_wraps_week() {
+        // 星期日晚上到星期一早上
+        assert_eq!(alarm(7, 0, MONDAY).next(at(6, 23, 0)), Some(8 * HOUR_MS));
+        assert_eq!(
+            alarm(7, 0, DAY_PATTERNS[1]).next(at(4, 8, 0)),
+            Some(2 * DAY_MS + 23 * HOUR_MS)
+        );
+        // 只在星期一响，刚过时间要等到下周
+        assert_eq!(
+            alarm(7, 0, MONDAY).next(at(0, 7, 1)),
+            Some(WEEK_MS - MINUTE_MS)
+        );
+        assert_eq!(alarm(0, 0, SUNDAY).next(at(6, 0, 0)), Some(WEEK_MS));
+        assert_eq!(alarm(7, 0, 0).next(at(0, 0, 0)), None);
+    }
+
+    #[test]
+    fn due_across_week() {
+        let alarms = [alarm(0, 0, MONDAY), alarm(23, 59, SUNDAY)];
+        let since = at(6, 23, 58);
+        assert_eq!(next_alarm(&alarms, since), Some((1, MINUTE_MS)));
+        assert_eq!(due(&alarms, since, MINUTE_MS / 2), None);
+        assert_eq!(due(&alarms, since, MINUTE_MS), Some(1));
+        // 星期一 0 点的闹钟在一周结束后响
+        let since = at(6, 23, 59) + 1;
+        assert_eq!(due(&alarms, since, MINUTE_MS - 2), None);
+        assert_eq!(due(&alarms, since, MINUTE_MS), Some(0));
+    }
+
+    #[test]
+    fn clock_wraps_week() {
+        let clock = Clock::new(5_000, 6, 23 * 60 + 59);
+        assert_eq!(clock.now(5_000), WEEK_MS - MINUTE_MS);
+        assert_eq!(clock.now(5_000 + 2 * MINUTE_MS as u64), MINUTE_MS);
+        assert_eq!(clock.time_of_day(5_000 + 2 * MINUTE_MS as u64), (0, 1));
+        // RTC 时间倒退时停在设置的时刻
+        assert_eq!(clock.now(0), WEEK_MS - MINUTE_MS);
+        assert_eq!(Clock::new(0, 7, 0).now(0), 0);
+
+        let (rtc_ms, week_ms) = clock.to_parts();
+        assert_eq!(Clock::from_parts(rtc_ms, week_ms), Some(clock));
+        assert_eq!(Clock::from_parts(rtc_ms, WEEK_MS), None);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let alarm = Alarm {
+            enabled: true,
+            hour: 23,
+            minute: 59,
+            days: SUNDAY,
+            preset: PRESET_COUNT - 1,
+            volume: MAX_VOLUME,
+            ramp: MAX_RAMP,
+            duration: MAX_DURATION,
+        };
+        assert_eq!(Alarm::from_bytes(alarm.to_bytes()), Some(alarm));
+        let mut bytes = alarm.to_bytes();
+        bytes[1] = 24;
+        assert_eq!(Alarm::from_bytes(bytes), None);
+        let mut bytes = alarm.to_bytes();
+        bytes[7] = 0;
+        assert_eq!(Alarm::from_bytes(bytes), None);
+    }
+}
@@ -0,0 +1,35 @@
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum EventType {
+    KeyShort,
+    KeyLongStart,
+    KeyLongIng,
+    KeyLongEnd,
+    EC11Front,
+    EC11Back,
+    // 按住旋钮的同时转动
+    EC11PressedFront,
+    EC11PressedBack,
+    // 按住转动后松开
+    EC11PressedEnd,
+}
+
+impl EventType {
+    /// 旋钮反向安装时交换转动方向，其他事件不变
+    pub fn reversed(&self) -> EventType {
+        match self {
+            EventType::EC11Front => EventType::EC11Back,
+            EventType::EC11Back => EventType::EC11Front,
+            EventType::EC11PressedFront => EventType::EC11PressedBack,
+            EventType::EC11PressedBack => EventType::EC11PressedFront,
+            kind => *kind,
+        }
+    }
+
+    /// 按键释放时产生的事件，收到后本次按键检测结束
+    pub fn is_key_end(&self) -> bool {
+        matches!(
+            self,
+            EventType::KeyShort | EventType::KeyLongEnd | EventType::EC11PressedEnd
+        )
+    }
+}
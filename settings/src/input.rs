@@ -0,0 +1,31 @@
+use crate::event::EventType;
+
+/// 逻辑输入，与具体 GPIO 无关
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum InputId {
+    Sw1,
+    Sw2,
+    Sw3,
+    Ec11,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct InputEvent {
+    pub source: InputId,
+    pub kind: EventType,
+    /// 旋钮转动速度(步/秒)，按键事件为 0
+    pub velocity: f32,
+    /// 按旋钮加速曲线换算出的步长倍数，按键事件为 1
+    pub multiplier: u32,
+}
+
+impl InputEvent {
+    pub fn key(source: InputId, kind: EventType) -> Self {
+        InputEvent {
+            source,
+            kind,
+            velocity: 0.0,
+            multiplier: 1,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+#![no_std]
+
+pub mod action;
+pub mod alarm;
+pub mod event;
+pub mod input;
+pub mod screen;
+pub mod settings;
+pub mod tuner;
@@ -0,0 +1,116 @@
+use embassy_time::Duration;
+
+/// 菜单里可选的超时时间(秒)，0 表示从不
+pub const TIMEOUT_OPTIONS: [u16; 8] = [0, 10, 30, 60, 120, 300, 600, 1800];
+
+/// 对比度的档数，1 最暗
+pub const CONTRAST_LEVELS: u8 = 5;
+
+/// 没有操作时屏幕的状态，依次变暗、显示屏保、关闭
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum ScreenState {
+    On,
+    Dimmed,
+    Saver,
+    Off,
+}
+
+impl ScreenState {
+    /// 是否显示正常的界面，屏保和关闭时不绘制
+    pub fn shows_content(&self) -> bool {
+        matches!(self, ScreenState::On | ScreenState::Dimmed)
+    }
+}
+
+/// 防止 OLED 烧屏的超时设置，单位秒，0 表示不进入这个状态
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct ScreenTimeouts {
+    pub dim: u16,
+    pub saver: u16,
+    pub off: u16,
+}
+
+impl Default for ScreenTimeouts {
+    fn default() -> Self {
+        ScreenTimeouts {
+            dim: 30,
+            saver: 120,
+            off: 600,
+        }
+    }
+}
+
+impl ScreenTimeouts {
+    /// 没有操作 `idle` 后的状态，超时时间不需要按顺序设置
+    pub fn state(&self, idle: Duration) -> ScreenState {
+        let passed = |timeout: u16| timeout != 0 && idle >= seconds(timeout);
+        if passed(self.off) {
+            ScreenState::Off
+        } else if passed(self.saver) {
+            ScreenState::Saver
+        } else if passed(self.dim) {
+            ScreenState::Dimmed
+        } else {
+            ScreenState::On
+        }
+    }
+
+    /// 距离下一次状态变化的时间，不会再变化时为 None
+    pub fn next_change(&self, idle: Duration) -> Option<Duration> {
+        [self.dim, self.saver, self.off]
+            .into_iter()
+            .filter(|timeout| *timeout != 0)
+            .map(seconds)
+            .filter(|timeout| *timeout > idle)
+            .min()
+            .map(|timeout| timeout - idle)
+    }
+}
+
+/// 屏幕的安装方向和显示效果
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct DisplayOptions {
+    /// 旋转 180°，屏幕倒过来装进外壳时使用
+    pub flipped: bool,
+    /// 对比度档位，1..=[`CONTRAST_LEVELS`]
+    pub contrast: u8,
+    pub inverted: bool,
+    /// 旋转后旋钮方向也反过来
+    pub flip_knob: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            flipped: false,
+            contrast: 3,
+            inverted: false,
+            flip_knob: false,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// 旋钮事件是否需要反向
+    pub fn knob_reversed(&self) -> bool {
+        self.flipped && self.flip_knob
+    }
+}
+
+/// 在 [`TIMEOUT_OPTIONS`] 中切换到下一个或上一个，到两端为止
+pub fn step_timeout(timeout: u16, up: bool) -> u16 {
+    let index = TIMEOUT_OPTIONS
+        .iter()
+        .position(|option| *option >= timeout)
+        .unwrap_or(TIMEOUT_OPTIONS.len() - 1);
+    let index = if up {
+        (index + 1).min(TIMEOUT_OPTIONS.len() - 1)
+    } else {
+        index.saturating_sub(1)
+    };
+    TIMEOUT_OPTIONS[index]
+}
+
+fn seconds(timeout: u16) -> Duration {
+    Duration::from_secs(timeout as u64)
+}
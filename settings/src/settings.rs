@@ -0,0 +1,398 @@
+use esp32c3_fm_preset::{Band, Preset, NAME_LEN};
+
+use crate::action::{Action, Binding, Keymap, KEYMAP_LEN, PRESET_COUNT};
+use crate::alarm::{Alarm, ALARM_COUNT};
+use crate::event::EventType;
+use crate::input::InputId;
+use crate::screen::{DisplayOptions, ScreenTimeouts, CONTRAST_LEVELS};
+use crate::tuner::{AudioConfig, MAX_SEEK_THRESHOLD, MAX_VOLUME};
+
+const MAGIC: [u8; 2] = *b"FM";
+// 结构变化时加 1，旧版本数据直接丢弃使用默认值
+const VERSION: u8 = 7;
+/// 频率 + 波段 + 节目类型 + 电台名
+const PRESET_LEN: usize = 4 + 1 + 1 + NAME_LEN;
+const PRESETS_LEN: usize = PRESET_COUNT as usize * PRESET_LEN;
+const ALARMS_LEN: usize = ALARM_COUNT * Alarm::ENCODED_LEN;
+
+/// 需要保存到 flash 的设置
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Settings {
+    /// 上次收听的频率(kHz)
+    pub frequency: u32,
+    pub volume: u8,
+    pub seek_threshold: u8,
+    /// 关闭时只用耳机收听，喇叭功放保持关闭
+    pub speaker: bool,
+    pub audio: AudioConfig,
+    pub keymap: Keymap,
+    /// 预设电台，频率为 0 表示未保存
+    pub presets: [Preset; PRESET_COUNT as usize],
+    pub alarms: [Alarm; ALARM_COUNT],
+    pub screen: ScreenTimeouts,
+    pub display: DisplayOptions,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            frequency: 87_500,
+            volume: 8,
+            seek_threshold: 8,
+            speaker: true,
+            audio: AudioConfig::default(),
+            keymap: Keymap::default(),
+            presets: [Preset::EMPTY; PRESET_COUNT as usize],
+            alarms: [Alarm::default(); ALARM_COUNT],
+            screen: ScreenTimeouts::default(),
+            display: DisplayOptions::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// magic + 版本 + 频率 + 音量 + 搜台阈值 + 喇叭 + 音频效果 + 映射表 + 预设 + 闹钟 + 屏幕超时 + 屏幕显示 + 校验
+    pub const ENCODED_LEN: usize =
+        2 + 1 + 4 + 1 + 1 + 1 + 1 + KEYMAP_LEN * 4 + PRESETS_LEN + ALARMS_LEN + 6 + 4 + 2;
+
+    pub fn preset(&self, index: u8) -> Option<&Preset> {
+        self.presets
+            .get(index as usize)
+            .filter(|preset| !preset.is_empty())
+    }
+
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        let mut buf = [0u8; Self::ENCODED_LEN];
+        let mut writer = Writer {
+            buf: &mut buf,
+            pos: 0,
+        };
+        writer.put(&MAGIC);
+        writer.put(&[VERSION]);
+        writer.put(&self.frequency.to_le_bytes());
+        writer.put(&[self.volume, self.seek_threshold, self.speaker as u8]);
+        writer.put(&[encode_audio(&self.audio)]);
+        for binding in self.keymap.bindings.iter() {
+            writer.put(&[binding.source as u8, binding.kind as u8]);
+            writer.put(&binding.action.to_bytes());
+        }
+        for preset in self.presets.iter() {
+            writer.put(&preset.frequency.to_le_bytes());
+            writer.put(&[preset.band as u8, preset.pty]);
+            writer.put(&preset.name);
+        }
+        for alarm in self.alarms.iter() {
+            writer.put(&alarm.to_bytes());
+        }
+        for timeout in [self.screen.dim, self.screen.saver, self.screen.off] {
+            writer.put(&timeout.to_le_bytes());
+        }
+        let display = &self.display;
+        writer.put(&[
+            display.flipped as u8,
+            display.contrast,
+            display.inverted as u8,
+            display.flip_knob as u8,
+        ]);
+        let pos = writer.pos;
+        let checksum = fletcher16(&buf[..pos]);
+        buf[pos..].copy_from_slice(&checksum.to_le_bytes());
+        buf
+    }
+
+    /// 数据损坏或版本不一致时返回 `None`
+    pub fn decode(buf: &[u8]) -> Option<Settings> {
+        if buf.len() < Self::ENCODED_LEN {
+            return None;
+        }
+        let (data, checksum) = buf[..Self::ENCODED_LEN].split_at(Self::ENCODED_LEN - 2);
+        if fletcher16(data).to_le_bytes() != checksum {
+            return None;
+        }
+        let mut reader = Reader { buf: data, pos: 0 };
+        if reader.take::<2>() != MAGIC || reader.take::<1>() != [VERSION] {
+            return None;
+        }
+        let frequency = u32::from_le_bytes(reader.take());
+        let [volume, seek_threshold, speaker] = reader.take();
+        let [audio] = reader.take();
+        let mut keymap = Keymap::default();
+        for binding in keymap.bindings.iter_mut() {
+            let [source, kind] = reader.take();
+            *binding = Binding {
+                source: input_id_from_u8(source)?,
+                kind: event_type_from_u8(kind)?,
+                action: Action::from_bytes(reader.take())?,
+            };
+        }
+        keymap.restore_fixed();
+        let mut presets = [Preset::EMPTY; PRESET_COUNT as usize];
+        for preset in presets.iter_mut() {
+            let frequency = u32::from_le_bytes(reader.take());
+            let [band, pty] = reader.take();
+            let name: [u8; NAME_LEN] = reader.take();
+            *preset = Preset {
+                frequency,
+                band: Band::from_u8(band)?,
+                pty,
+                ..Preset::EMPTY
+            }
+            .with_name(core::str::from_utf8(&name).ok()?);
+        }
+        let mut alarms = [Alarm::default(); ALARM_COUNT];
+        for alarm in alarms.iter_mut() {
+            *alarm = Alarm::from_bytes(reader.take())?;
+        }
+        let screen = ScreenTimeouts {
+            dim: u16::from_le_bytes(reader.take()),
+            saver: u16::from_le_bytes(reader.take()),
+            off: u16::from_le_bytes(reader.take()),
+        };
+        let [flipped, contrast, inverted, flip_knob] = reader.take();
+        let display = DisplayOptions {
+            flipped: flipped != 0,
+            contrast: contrast.clamp(1, CONTRAST_LEVELS),
+            inverted: inverted != 0,
+            flip_knob: flip_knob != 0,
+        };
+        Some(Settings {
+            frequency,
+            volume: volume.min(MAX_VOLUME),
+            seek_threshold: seek_threshold.min(MAX_SEEK_THRESHOLD),
+            speaker: speaker != 0,
+            audio: decode_audio(audio),
+            keymap,
+            presets,
+            alarms,
+            screen,
+            display,
+        })
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.buf[self.pos..self.pos + N]);
+        self.pos += N;
+        bytes
+    }
+}
+
+fn fletcher16(data: &[u8]) -> u16 {
+    let mut sum1: u16 = 0;
+    let mut sum2: u16 = 0;
+    for byte in data {
+        sum1 = (sum1 + *byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+/// 每个开关占一位
+fn encode_audio(audio: &AudioConfig) -> u8 {
+    audio.bass as u8
+        | (audio.mono as u8) << 1
+        | (audio.soft_mute as u8) << 2
+        | (audio.soft_blend as u8) << 3
+}
+
+fn decode_audio(bits: u8) -> AudioConfig {
+    AudioConfig {
+        bass: bits & 1 != 0,
+        mono: bits & 1 << 1 != 0,
+        soft_mute: bits & 1 << 2 != 0,
+        soft_blend: bits & 1 << 3 != 0,
+    }
+}
+
+fn input_id_from_u8(value: u8) -> Option<InputId> {
+    match value {
+        0 => Some(InputId::Sw1),
+        1 => Some(InputId::Sw2),
+        2 => Some(InputId::Sw3),
+        3 => Some(InputId::Ec11),
+        _ => None,
+    }
+}
+
+fn event_type_from_u8(value: u8) -> Option<EventType> {
+    match value {
+        0 => Some(EventType::KeyShort),
+        1 => Some(EventType::KeyLongStart),
+        2 => Some(EventType::KeyLongIng),
+        3 => Some(EventType::KeyLongEnd),
+        4 => Some(EventType::EC11Front),
+        5 => Some(EventType::EC11Back),
+        6 => Some(EventType::EC11PressedFront),
+        7 => Some(EventType::EC11PressedBack),
+        8 => Some(EventType::EC11PressedEnd),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每一项都和默认值不同
+    fn changed() -> Settings {
+        let mut settings = Settings {
+            frequency: 101_700,
+            volume: 12,
+            seek_threshold: 3,
+            speaker: false,
+            audio: AudioConfig {
+                bass: false,
+                mono: true,
+                soft_mute: false,
+                soft_blend: false,
+            },
+            screen: ScreenTimeouts {
+                dim: 10,
+                saver: 60,
+                off: 1800,
+            },
+            display: DisplayOptions {
+                flipped: true,
+                contrast: CONTRAST_LEVELS,
+                inverted: true,
+                flip_knob: true,
+            },
+            ..Settings::default()
+        };
+        settings
+            .keymap
+            .set(InputId::Sw3, EventType::KeyShort, Action::PresetRecall(2));
+        settings.presets[0] = Preset {
+            band: Band::World,
+            pty: 10,
+            ..Preset::new(97_400)
+        }
+        .with_name("RADIO 1");
+        settings.presets[PRESET_COUNT as usize - 1] = Preset::new(88_100);
+        settings.alarms[1] = Alarm {
+            enabled: true,
+            hour: 6,
+            minute: 45,
+            days: 0b110_0000,
+            preset: 1,
+            volume: 4,
+            ramp: 0,
+            duration: 90,
+        };
+        settings
+    }
+
+    /// 改动一个字节后重新计算校验
+    fn patched(index: usize, value: u8) -> [u8; Settings::ENCODED_LEN] {
+        let mut buf = changed().encode();
+        buf[index] = value;
+        let len = Settings::ENCODED_LEN - 2;
+        let checksum = fletcher16(&buf[..len]);
+        buf[len..].copy_from_slice(&checksum.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = changed();
+        assert_ne!(settings, Settings::default());
+        assert_eq!(Settings::decode(&settings.encode()), Some(settings));
+        let settings = Settings::default();
+        assert_eq!(Settings::decode(&settings.encode()), Some(settings));
+    }
+
+    #[test]
+    fn longer_buffer() {
+        // 只看开头 ENCODED_LEN 字节，后面多出的内容不影响
+        let mut buf = [0xff; Settings::ENCODED_LEN + 16];
+        buf[..Settings::ENCODED_LEN].copy_from_slice(&changed().encode());
+        assert_eq!(Settings::decode(&buf), Some(changed()));
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let buf = changed().encode();
+        for index in [
+            0,
+            2,
+            3,
+            Settings::ENCODED_LEN - 3,
+            Settings::ENCODED_LEN - 1,
+        ] {
+            let mut corrupted = buf;
+            corrupted[index] ^= 0x10;
+            assert_eq!(Settings::decode(&corrupted), None, "byte {}", index);
+        }
+        // 擦除后的 flash
+        assert_eq!(Settings::decode(&[0xff; Settings::ENCODED_LEN]), None);
+        assert_eq!(Settings::decode(&[0; Settings::ENCODED_LEN]), None);
+    }
+
+    #[test]
+    fn rejects_other_version() {
+        assert_eq!(Settings::decode(&patched(2, VERSION - 1)), None);
+        assert_eq!(Settings::decode(&patched(2, VERSION + 1)), None);
+        assert_eq!(Settings::decode(&patched(0, b'X')), None);
+        assert_eq!(Settings::decode(&patched(2, VERSION)), Some(changed()));
+    }
+
+    #[test]
+    fn rejects_short_buffer() {
+        let buf = changed().encode();
+        assert_eq!(Settings::decode(&buf[..Settings::ENCODED_LEN - 1]), None);
+        assert_eq!(Settings::decode(&[]), None);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        // 第一个绑定的输入编号
+        assert_eq!(Settings::decode(&patched(11, 4)), None);
+        // 第一个绑定的事件类型
+        assert_eq!(Settings::decode(&patched(12, 9)), None);
+    }
+
+    #[test]
+    fn clamps_values() {
+        let settings = Settings::decode(&patched(7, MAX_VOLUME + 5)).unwrap();
+        assert_eq!(settings.volume, MAX_VOLUME);
+        let settings = Settings::decode(&patched(8, u8::MAX)).unwrap();
+        assert_eq!(settings.seek_threshold, MAX_SEEK_THRESHOLD);
+        let contrast = Settings::ENCODED_LEN - 2 - 3;
+        let settings = Settings::decode(&patched(contrast, 0)).unwrap();
+        assert_eq!(settings.display.contrast, 1);
+    }
+
+    #[test]
+    fn restores_fixed_binding() {
+        let mut settings = changed();
+        let index = settings
+            .keymap
+            .bindings
+            .iter()
+            .position(Binding::is_fixed)
+            .unwrap();
+        settings.keymap.bindings[index].action = Action::None;
+        let decoded = Settings::decode(&settings.encode()).unwrap();
+        assert_eq!(decoded.keymap, changed().keymap);
+        assert_eq!(decoded.keymap.bindings[index].action, Action::OpenMenu);
+    }
+}
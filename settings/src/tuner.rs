@@ -0,0 +1,27 @@
+pub use esp32c3_fm_command::MAX_VOLUME;
+/// 搜台阈值寄存器 SEEKTH 4 位，数值越低搜到的台越多
+pub const MAX_SEEK_THRESHOLD: u8 = 15;
+
+/// 音频效果设置
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct AudioConfig {
+    /// 低音增强
+    pub bass: bool,
+    /// 强制单声道
+    pub mono: bool,
+    /// 信号弱时自动降低音量
+    pub soft_mute: bool,
+    /// 信号弱时立体声逐渐混合为单声道
+    pub soft_blend: bool,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            bass: true,
+            mono: false,
+            soft_mute: true,
+            soft_blend: true,
+        }
+    }
+}
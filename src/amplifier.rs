@@ -0,0 +1,102 @@
+use embassy_time::{Duration, Timer};
+use esp_hal::gpio::{Level, Output, OutputPin};
+
+/// 功放开关配置
+#[derive(Debug, Copy, Clone)]
+pub struct AmplifierConfig {
+    /// 功放工作时引脚的电平，spk_sw 低电平打开喇叭
+    pub active_level: Level,
+    /// 收音机取消静音后等待多久再打开功放，避开解除静音时的爆音
+    pub pop_delay: Duration,
+}
+
+impl Default for AmplifierConfig {
+    fn default() -> Self {
+        AmplifierConfig {
+            active_level: Level::Low,
+            pop_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+/// 喇叭功放的电源开关(spk_sw)
+///
+/// 换台/搜台、静音、只用耳机和待机任意一个成立时关闭功放，全部解除后延迟打开
+pub struct Amplifier<P>
+where
+    P: OutputPin + 'static,
+{
+    pin: Output<'static, P>,
+    config: AmplifierConfig,
+    speaker: bool,
+    muted: bool,
+    tuning: bool,
+    standby: bool,
+    on: bool,
+}
+
+impl<P> Amplifier<P>
+where
+    P: OutputPin + 'static,
+{
+    /// 初始为关闭状态，由调用者在收音机启动后打开
+    pub fn new(mut pin: Output<'static, P>, config: AmplifierConfig) -> Self {
+        pin.set_level(inactive(config.active_level));
+        Amplifier {
+            pin,
+            config,
+            speaker: true,
+            muted: false,
+            tuning: true,
+            standby: false,
+            on: false,
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// 关闭后只能用耳机收听
+    pub async fn set_speaker(&mut self, speaker: bool) {
+        self.speaker = speaker;
+        self.apply().await
+    }
+
+    pub async fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply().await
+    }
+
+    /// 换台/搜台开始前置为 true，收音机恢复输出后置为 false
+    pub async fn set_tuning(&mut self, tuning: bool) {
+        self.tuning = tuning;
+        self.apply().await
+    }
+
+    pub async fn set_standby(&mut self, standby: bool) {
+        self.standby = standby;
+        self.apply().await
+    }
+
+    async fn apply(&mut self) {
+        let on = self.speaker && !self.muted && !self.tuning && !self.standby;
+        if on == self.on {
+            return;
+        }
+        if on {
+            Timer::after(self.config.pop_delay).await;
+            self.pin.set_level(self.config.active_level);
+        } else {
+            self.pin.set_level(inactive(self.config.active_level));
+        }
+        self.on = on;
+    }
+}
+
+fn inactive(level: Level) -> Level {
+    match level {
+        Level::High => Level::Low,
+        Level::Low => Level::High,
+    }
+}
@@ -4,51 +4,29 @@
 use embassy_executor::Spawner;
 #[allow(unused)]
 use esp_backtrace as _;
-use esp_hal::gpio::{GpioPin, Input, Io, Pull};
-use esp_hal::timer::timg::TimerGroup;
-use esp_hal::timer::{ErasedTimer, OneShotTimer};
-use esp_hal::{clock::ClockControl, peripherals::Peripherals, prelude::*, system::SystemControl};
+use esp_hal::peripherals::Peripherals;
+use esp_hal::prelude::*;
 use esp_println::println;
-use static_cell::StaticCell;
 
-use esp32c3_fm::ec11::ec11_detection;
-
-static ONE_SHOT_TIMER: StaticCell<[OneShotTimer<ErasedTimer>; 1]> = StaticCell::new();
+use esp32c3_fm::board::{Board, Ec11APin, Ec11BPin, Ec11KeyPin};
+use esp32c3_fm::ec11::{Ec11, Ec11Config, SampledBackend, DEFAULT_SAMPLE_PERIOD};
 
 #[embassy_executor::task]
-async fn ec11_run(
-    mut ec11_a: Input<'static, GpioPin<4>>,
-    mut ec11_b: Input<'static, GpioPin<5>>,
-    mut ec11_key: Input<'static, GpioPin<1>>,
-) {
-    ec11_detection(
-        &mut ec11_a,
-        &mut ec11_b,
-        &mut ec11_key,
-        |event_type, speed| {
-            println!("event type: {:?}, speed: {}", event_type, speed);
-        },
-    )
-    .await;
+async fn ec11_run(mut ec11: Ec11<SampledBackend<Ec11APin, Ec11BPin>, Ec11KeyPin>) {
+    loop {
+        let event = ec11.next_event().await;
+        println!("event type: {:?}, speed: {}", event.event_type, event.speed);
+    }
 }
 
 #[main]
 async fn main(spawner: Spawner) {
     esp_println::logger::init_logger_from_env();
-    let peripherals = Peripherals::take();
-    let system = SystemControl::new(peripherals.SYSTEM);
-    let clocks = ClockControl::boot_defaults(system.clock_control).freeze();
-
-    // initialize the timer(s)
-    let timer_group = TimerGroup::new(peripherals.TIMG0, &clocks, None);
-    let one_shot_timer = OneShotTimer::new(timer_group.timer0.into());
-    let timers_ref = ONE_SHOT_TIMER.init([one_shot_timer]);
-    esp_hal_embassy::init(&clocks, timers_ref);
-    let io = Io::new(peripherals.GPIO, peripherals.IO_MUX);
-    let ec11_a = Input::new(io.pins.gpio4, Pull::Up);
-    let ec11_b = Input::new(io.pins.gpio5, Pull::Up);
-    let ec11_key = Input::new(io.pins.gpio1, Pull::Up);
+    let board = Board::init(Peripherals::take());
+    // 定时采样 A/B，不使用 GPIO 中断
+    let backend = SampledBackend::new(board.ec11_a, board.ec11_b, DEFAULT_SAMPLE_PERIOD);
+    let ec11 = Ec11::with_backend(backend, board.ec11_key, Ec11Config::default());
 
-    spawner.spawn(ec11_run(ec11_a, ec11_b, ec11_key)).ok();
+    spawner.spawn(ec11_run(ec11)).ok();
     println!("Start!");
 }
@@ -0,0 +1,1336 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+#[cfg(not(feature = "console"))]
+use core::convert::Infallible;
+use core::future::pending;
+
+use embassy_executor::Spawner;
+use embassy_futures::select::{select3, Either3};
+#[cfg(feature = "console")]
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::Point;
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics::Drawable;
+#[allow(unused)]
+use esp_backtrace as _;
+use esp_hal::gpio::Input;
+use esp_hal::i2c::I2C;
+#[cfg(feature = "console")]
+use esp_hal::peripherals::USB_DEVICE;
+use esp_hal::peripherals::{Peripherals, I2C0};
+use esp_hal::prelude::*;
+#[cfg(feature = "console")]
+use esp_hal::reset::software_reset;
+use esp_hal::reset::{get_wakeup_cause, SleepSource};
+use esp_hal::rtc_cntl::Rtc;
+#[cfg(feature = "console")]
+use esp_hal::usb_serial_jtag::UsbSerialJtag;
+use esp_hal::Blocking;
+use esp_println::println;
+#[cfg(feature = "storage")]
+use esp_storage::FlashStorage;
+use log::{debug, error, info, warn};
+use rda5807m::register_address::StatusRegister;
+use rda5807m::{Address, Rda5708m};
+use shared_bus::{BusManagerSimple, I2cProxy, NullMutex};
+use ssd1306::mode::{BufferedGraphicsMode, DisplayConfig};
+use ssd1306::prelude::{Brightness, DisplayRotation, DisplaySize128x64, I2CInterface};
+use ssd1306::{I2CDisplayInterface, Ssd1306};
+
+use esp32c3_fm::acceleration::AccelerationProfile;
+use esp32c3_fm::action::Action;
+use esp32c3_fm::alarm::{self, Clock, SNOOZE, WEEK_MS};
+use esp32c3_fm::amplifier::{Amplifier, AmplifierConfig};
+#[cfg(feature = "battery")]
+use esp32c3_fm::battery::{Battery, BatteryLevel};
+#[cfg(feature = "battery")]
+use esp32c3_fm::board::BatterySense;
+use esp32c3_fm::board::{
+    self, AmplifierPin, Board, Ec11APin, Ec11BPin, Ec11KeyPin, Sw1Pin, Sw2Pin, Sw3Pin,
+};
+use esp32c3_fm::bus::{BusScan, BusState, DISPLAY_ADDRESSES};
+use esp32c3_fm::command::{MAX_FREQUENCY, MIN_FREQUENCY};
+#[cfg(feature = "console")]
+use esp32c3_fm::console::{self, Command, CommandChannel};
+use esp32c3_fm::ec11::{Ec11, Ec11Config, EdgeBackend};
+use esp32c3_fm::error::{Error, RetryPolicy};
+use esp32c3_fm::event::EventType;
+use esp32c3_fm::input::{self, InputBus, InputEvent, InputId};
+use esp32c3_fm::menu::{Menu, MenuInput, MenuResult, Page};
+use esp32c3_fm::preset::Preset;
+#[cfg(feature = "console")]
+use esp32c3_fm::preset::{self, Entry};
+#[cfg(feature = "rds")]
+use esp32c3_fm::rds::{self, ClockTime, Rds, RdsUpdate};
+use esp32c3_fm::screen::{DisplayOptions, ScreenState};
+use esp32c3_fm::settings::Settings;
+use esp32c3_fm::sleep::{Remaining, SleepTimer};
+#[cfg(feature = "storage")]
+use esp32c3_fm::storage::SettingsStorage;
+use esp32c3_fm::telemetry::{self, Record, STATUS_PERIOD};
+use esp32c3_fm::tuner::{Tuner, MAX_SEEK_THRESHOLD, MAX_VOLUME};
+use esp32c3_fm::ui;
+
+static INPUT_BUS: InputBus = InputBus::new();
+// 旋钮转得越快调频和菜单的步长越大，调频最快一次跳 1MHz
+const KNOB_ACCELERATION: AccelerationProfile = AccelerationProfile::Linear {
+    threshold: 5.0,
+    per_step: 3.0,
+    max: 10,
+};
+// 搜台设置页信号强度的刷新间隔
+const GAUGE_REFRESH: Duration = Duration::from_millis(500);
+// 读取 RDS 的间隔，一组数据大约 87ms
+#[cfg(feature = "rds")]
+const RDS_POLL: Duration = Duration::from_millis(40);
+// 睡眠定时的检查间隔，剩余秒数和渐弱音量按秒更新
+const SLEEP_POLL: Duration = Duration::from_secs(1);
+// 检查闹钟的间隔，响铃时也按这个间隔调大音量
+const ALARM_POLL: Duration = Duration::from_secs(1);
+// 屏保移动位置的间隔
+const SAVER_MOVE: Duration = Duration::from_secs(5);
+// 电池电压的采样间隔，取最近 16 次的平均值
+#[cfg(feature = "battery")]
+const BATTERY_POLL: Duration = Duration::from_secs(2);
+const RETAINED_MAGIC: u32 = 0x464D_434B;
+// 睡眠前没有设置闹钟
+const NO_ALARM: u8 = u8::MAX;
+
+/// 深度睡眠和软件复位后保留的状态
+///
+/// 上电时内容是随机的，用 magic 判断是否有效，所以只用整数
+#[derive(Copy, Clone)]
+struct Retained {
+    magic: u32,
+    clock_rtc_ms: u64,
+    clock_week_ms: u32,
+    /// 睡眠前设置的闹钟，定时器唤醒后响铃
+    alarm: u8,
+    /// 睡眠前的频率，0 表示没有需要恢复的状态
+    frequency: u32,
+    volume: u8,
+    muted: u8,
+}
+
+// SAFETY: 所有字段都是整数，任意内容都是有效的值
+unsafe impl esp_hal::Persistable for Retained {}
+
+#[ram(rtc_fast, persistent)]
+static mut RETAINED: Retained = Retained {
+    magic: 0,
+    clock_rtc_ms: 0,
+    clock_week_ms: 0,
+    alarm: NO_ALARM,
+    frequency: 0,
+    volume: 0,
+    muted: 0,
+};
+
+/// 睡眠前的收听状态，醒来后恢复
+#[derive(Copy, Clone)]
+struct Resume {
+    frequency: u32,
+    volume: u8,
+    muted: bool,
+}
+
+impl Retained {
+    fn load() -> Retained {
+        unsafe { core::ptr::addr_of!(RETAINED).read_volatile() }
+    }
+
+    fn store(clock: Option<Clock>, alarm: Option<usize>, resume: Option<Resume>) {
+        let (clock_rtc_ms, clock_week_ms) = clock.map_or((0, 0), |clock| clock.to_parts());
+        let resume = resume.unwrap_or(Resume {
+            frequency: 0,
+            volume: 0,
+            muted: false,
+        });
+        let retained = Retained {
+            magic: RETAINED_MAGIC,
+            clock_rtc_ms,
+            clock_week_ms,
+            alarm: alarm.map_or(NO_ALARM, |index| index as u8),
+            frequency: resume.frequency,
+            volume: resume.volume,
+            muted: resume.muted as u8,
+        };
+        unsafe { core::ptr::addr_of_mut!(RETAINED).write_volatile(retained) }
+    }
+
+    fn clock(&self) -> Option<Clock> {
+        if self.magic != RETAINED_MAGIC || self.clock_rtc_ms == 0 {
+            return None;
+        }
+        Clock::from_parts(self.clock_rtc_ms, self.clock_week_ms)
+    }
+
+    fn alarm(&self) -> Option<usize> {
+        let index = self.alarm as usize;
+        (self.magic == RETAINED_MAGIC && index < alarm::ALARM_COUNT).then_some(index)
+    }
+
+    fn resume(&self) -> Option<Resume> {
+        let valid =
+            self.magic == RETAINED_MAGIC && self.frequency != 0 && self.volume <= MAX_VOLUME;
+        valid.then_some(Resume {
+            frequency: self.frequency,
+            volume: self.volume,
+            muted: self.muted != 0,
+        })
+    }
+}
+
+#[embassy_executor::task]
+async fn ec11_run(ec11: Ec11<EdgeBackend<Ec11APin, Ec11BPin>, Ec11KeyPin>) {
+    input::ec11_run(&INPUT_BUS, InputId::Ec11, ec11).await
+}
+
+#[embassy_executor::task]
+async fn sw1_run(sw1_key: Input<'static, Sw1Pin>) {
+    input::key_run(&INPUT_BUS, InputId::Sw1, sw1_key).await
+}
+
+#[embassy_executor::task]
+async fn sw2_run(sw2_key: Input<'static, Sw2Pin>) {
+    input::key_run(&INPUT_BUS, InputId::Sw2, sw2_key).await
+}
+
+#[embassy_executor::task]
+async fn sw3_run(sw3_key: Input<'static, Sw3Pin>) {
+    input::key_run(&INPUT_BUS, InputId::Sw3, sw3_key).await
+}
+
+type Display<'a> = Ssd1306<
+    I2CInterface<I2cProxy<'a, NullMutex<I2C<'static, I2C0, Blocking>>>>,
+    DisplaySize128x64,
+    BufferedGraphicsMode<DisplaySize128x64>,
+>;
+type RadioTuner<'a> = Tuner<I2cProxy<'a, NullMutex<I2C<'static, I2C0, Blocking>>>>;
+
+fn text_style() -> MonoTextStyle<'static, BinaryColor> {
+    MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build()
+}
+
+fn draw_text(display: &mut Display<'_>, text: &str) -> Result<(), Error> {
+    Text::with_baseline(text, Point::new(0, 0), text_style(), Baseline::Top).draw(display)?;
+    display.flush()?;
+    display.clear(BinaryColor::Off)?;
+    Ok(())
+}
+
+/// 当前电台的 RDS 信息，关闭 rds feature 时始终为空
+#[derive(Default)]
+struct Station {
+    #[cfg(feature = "rds")]
+    rds: Rds,
+    /// 还没交给时钟的 RDS 时间
+    #[cfg(feature = "rds")]
+    time: Option<ClockTime>,
+}
+
+impl Station {
+    /// 换台后清空
+    fn reset(&mut self) {
+        #[cfg(feature = "rds")]
+        self.rds.reset();
+    }
+
+    fn program_service(&self) -> Option<&str> {
+        #[cfg(feature = "rds")]
+        {
+            self.rds.program_service()
+        }
+        #[cfg(not(feature = "rds"))]
+        {
+            None
+        }
+    }
+
+    fn program_type(&self) -> Option<&'static str> {
+        #[cfg(feature = "rds")]
+        {
+            self.rds.pi().map(|_| rds::pty_label(self.rds.pty()))
+        }
+        #[cfg(not(feature = "rds"))]
+        {
+            None
+        }
+    }
+
+    /// 读取一组 RDS 数据，返回主界面是否需要刷新
+    #[cfg(feature = "rds")]
+    async fn poll(&mut self, tuner: &mut RadioTuner<'_>) -> Result<bool, Error> {
+        let Some(group) = tuner.rds_group().await? else {
+            return Ok(false);
+        };
+        match self.rds.decode(&group) {
+            Some(RdsUpdate::ClockTime(time)) => {
+                debug!("rds time: {:?}", time);
+                self.time = Some(time);
+                Ok(false)
+            }
+            Some(update) => {
+                debug!("rds: {:?} {:?}", update, self.program_service());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    #[cfg(not(feature = "rds"))]
+    async fn poll(&mut self, _tuner: &mut RadioTuner<'_>) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// 取出新收到的本地时间(星期, 分钟)
+    fn take_time(&mut self) -> Option<(u8, u16)> {
+        #[cfg(feature = "rds")]
+        {
+            let time = self.time.take()?;
+            Some((time.local_weekday(), time.local_minutes()))
+        }
+        #[cfg(not(feature = "rds"))]
+        {
+            None
+        }
+    }
+
+    /// 保存预设时带上节目名和节目类型
+    fn preset(&self, frequency: u32) -> Preset {
+        #[cfg(feature = "rds")]
+        if self.rds.pi().is_some() {
+            let preset = Preset {
+                pty: self.rds.pty(),
+                ..Preset::new(frequency)
+            };
+            return preset.with_name(self.program_service().unwrap_or(""));
+        }
+        Preset::new(frequency)
+    }
+
+    /// 遥测用的 RDS 记录，还没收到 RDS 时为 None
+    fn record(&self) -> Option<Record<'_>> {
+        #[cfg(feature = "rds")]
+        {
+            Some(Record::Rds {
+                pi: self.rds.pi()?,
+                pty: self.rds.pty(),
+                program_service: self.rds.program_service(),
+            })
+        }
+        #[cfg(not(feature = "rds"))]
+        {
+            None
+        }
+    }
+}
+
+/// 闹钟响铃的状态
+#[derive(Copy, Clone)]
+enum Wake {
+    Ringing {
+        index: usize,
+        since: Instant,
+    },
+    /// 贪睡，到时间后再次响铃
+    Snoozed {
+        index: usize,
+        until: Instant,
+    },
+}
+
+/// 收音机的全部状态，按键和串口命令都在这里执行
+struct Radio<'a> {
+    tuner: RadioTuner<'a>,
+    display: Option<Display<'a>>,
+    amplifier: Amplifier<AmplifierPin>,
+    station: Station,
+    settings: Settings,
+    menu: Option<Menu>,
+    #[cfg(feature = "storage")]
+    storage: SettingsStorage<FlashStorage>,
+    /// 是否输出遥测数据
+    telemetry: bool,
+    last_report: Instant,
+    sleep: SleepTimer,
+    /// 屏幕上正在显示的剩余时间
+    shown_sleep: Option<Remaining>,
+    rtc: Rtc<'static>,
+    /// 用 RDS 时间校准的星期时钟
+    clock: Option<Clock>,
+    /// 上次检查闹钟时的星期时钟
+    alarm_checked: Option<u32>,
+    alarm: Option<Wake>,
+    /// 上次输入的时间，用来变暗、显示屏保和关闭屏幕
+    last_input: Instant,
+    screen: ScreenState,
+    /// 屏保当前的位置
+    saver_step: u32,
+    #[cfg(feature = "battery")]
+    battery_sense: BatterySense,
+    #[cfg(feature = "battery")]
+    battery: Battery,
+    #[cfg(feature = "battery")]
+    last_battery: Instant,
+    /// 屏幕上正在显示的电量
+    #[cfg(feature = "battery")]
+    shown_battery: Option<u8>,
+}
+
+impl<'a> Radio<'a> {
+    /// 恢复设置并开始播放
+    async fn start(&mut self) -> Result<(), Error> {
+        self.apply_display()?;
+        self.tuner.start(self.settings.volume).await?;
+        self.tuner.set_audio(&self.settings.audio).await?;
+        self.tuner
+            .set_seek_threshold(self.settings.seek_threshold)
+            .await?;
+        self.amplifier.set_speaker(self.settings.speaker).await;
+        self.tune(self.settings.frequency).await?;
+        self.refresh().await
+    }
+
+    /// 保存设置，没有 storage feature 时什么都不做
+    fn save(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "storage")]
+        self.storage
+            .save(&self.settings)
+            .map_err(|_| Error::Storage)?;
+        Ok(())
+    }
+
+    async fn refresh(&mut self) -> Result<(), Error> {
+        let status = self.tuner.status().await?;
+        self.refresh_status(status).await
+    }
+
+    async fn refresh_status(&mut self, status: StatusRegister) -> Result<(), Error> {
+        let freq = self.tuner.read_frequency().await?;
+        let rssi = self.tuner.rssi().await?;
+        debug!("freq:{}, rssi:{}, status:{:?}", freq, rssi, status);
+        self.shown_sleep = self.sleep.remaining(Instant::now());
+        #[cfg(feature = "battery")]
+        let (battery, battery_low) = {
+            self.shown_battery = self.battery.percent();
+            let low = self.battery.level() != BatteryLevel::Normal;
+            (self.shown_battery, low)
+        };
+        #[cfg(not(feature = "battery"))]
+        let (battery, battery_low) = (None, false);
+        // 没有屏幕或屏幕休眠时只打印状态
+        let Some(display) = self.display.as_mut() else {
+            return Ok(());
+        };
+        if !self.screen.shows_content() {
+            return Ok(());
+        }
+        let screen = ui::MainScreen {
+            frequency: freq,
+            rssi,
+            stereo: status.st,
+            volume: self.tuner.volume(),
+            muted: self.tuner.is_muted(),
+            program_service: self.station.program_service(),
+            program_type: self.station.program_type(),
+            sleep: self.shown_sleep,
+            battery,
+            battery_low,
+        };
+        ui::draw_main_screen(display, &screen)?;
+        display.flush()?;
+        display.clear(BinaryColor::Off)?;
+        Ok(())
+    }
+
+    async fn draw_menu(&mut self) -> Result<(), Error> {
+        let (Some(display), Some(menu)) = (self.display.as_mut(), self.menu.as_ref()) else {
+            return Ok(());
+        };
+        if !self.screen.shows_content() {
+            return Ok(());
+        }
+        let mut text = String::new();
+        menu.render(&self.settings, &mut text).ok();
+        if menu.page() != Page::Seek {
+            return draw_text(display, text.as_str());
+        }
+        // 搜台设置页显示当前信号强度，方便对着真实电台调整
+        let rssi = self.tuner.rssi().await?;
+        Text::with_baseline(text.as_str(), Point::new(0, 0), text_style(), Baseline::Top)
+            .draw(display)?;
+        ui::draw_rssi_gauge(display, rssi, self.settings.seek_threshold)?;
+        display.flush()?;
+        display.clear(BinaryColor::Off)?;
+        Ok(())
+    }
+
+    /// 按设置调整屏幕方向、对比度和反色
+    fn apply_display(&mut self) -> Result<(), Error> {
+        let Some(display) = self.display.as_mut() else {
+            return Ok(());
+        };
+        let options = self.settings.display;
+        display.set_rotation(display_rotation(&options))?;
+        display.set_invert(options.inverted)?;
+        if self.screen != ScreenState::Dimmed {
+            display.set_brightness(brightness(options.contrast))?;
+        }
+        Ok(())
+    }
+
+    /// 显示错误页面，屏幕本身出错时重新初始化屏幕
+    fn show_error(&mut self, error: Error) {
+        error!("{}", error);
+        let Some(display) = self.display.as_mut() else {
+            return;
+        };
+        let drawn = if error == Error::Display {
+            Err(error)
+        } else {
+            ui::draw_error_screen(display, &error)
+                .and_then(|_| display.flush())
+                .and_then(|_| display.clear(BinaryColor::Off))
+                .map_err(Error::from)
+        };
+        if drawn.is_err() {
+            if let Err(e) = display.init() {
+                error!("init display: {:?}", e);
+            }
+            // 初始化后恢复默认的对比度和反色
+            self.apply_display().ok();
+        }
+    }
+
+    async fn tune(&mut self, freq: u32) -> Result<(), Error> {
+        // 换台前关闭功放，收音机恢复输出后再打开
+        self.station.reset();
+        self.amplifier.set_tuning(true).await;
+        let result = self.tuner.tune(freq).await;
+        self.amplifier.set_tuning(false).await;
+        result?;
+        self.settings.frequency = freq;
+        Ok(())
+    }
+
+    async fn seek(&mut self, up: bool) -> Result<(), Error> {
+        // 搜台过程中静音并关闭功放，搜到后渐强
+        self.amplifier.set_tuning(true).await;
+        self.station.reset();
+        let result = async {
+            self.tuner.fade_out().await?;
+            self.tuner.seek(up).await?;
+            Timer::after(Duration::from_millis(1_00)).await;
+            loop {
+                let status = self.tuner.status().await?;
+                if status.stc {
+                    break;
+                }
+                self.refresh_status(status).await?;
+                Timer::after(Duration::from_millis(1_000)).await;
+            }
+            self.settings.frequency = self.tuner.read_frequency().await?;
+            self.tuner.fade_in().await
+        }
+        .await;
+        self.amplifier.set_tuning(false).await;
+        result?;
+        self.refresh().await
+    }
+
+    /// 串口的 `vol` 命令使用
+    #[cfg(feature = "console")]
+    async fn set_volume(&mut self, volume: u8) -> Result<(), Error> {
+        self.tuner.set_volume(volume).await?;
+        self.settings.volume = self.tuner.volume();
+        self.refresh().await
+    }
+
+    /// 输出一条遥测数据
+    fn report(&self, record: Record) {
+        if !self.telemetry {
+            return;
+        }
+        let mut line = String::new();
+        record.write(Instant::now().as_millis(), &mut line).ok();
+        println!("{}", line);
+    }
+
+    async fn report_status(&mut self) -> Result<(), Error> {
+        let status = self.tuner.status().await?;
+        let record = Record::Status {
+            frequency: self.tuner.read_frequency().await?,
+            rssi: self.tuner.rssi().await?,
+            stereo: status.st,
+            volume: self.tuner.volume(),
+            muted: self.tuner.is_muted(),
+        };
+        self.report(record);
+        Ok(())
+    }
+
+    /// 睡眠定时到时关机，最后一分钟逐渐降低音量
+    async fn poll_sleep(&mut self) -> Result<(), Error> {
+        let now = Instant::now();
+        if self.sleep.is_expired(now) {
+            info!("sleep timer expired");
+            self.power_off().await;
+        }
+        // 只改变收音机的音量，设置里保留原来的音量
+        let volume = self.sleep.volume(self.settings.volume, now);
+        if volume != self.tuner.volume() {
+            self.tuner.set_volume(volume).await?;
+        }
+        if self.menu.is_none() && self.sleep.remaining(now) != self.shown_sleep {
+            self.refresh().await?;
+        }
+        Ok(())
+    }
+
+    /// 采样电池电压，电量变化时刷新，低于关机电压时关机
+    #[cfg(feature = "battery")]
+    async fn poll_battery(&mut self) -> Result<(), Error> {
+        if self.last_battery.elapsed() < BATTERY_POLL {
+            return Ok(());
+        }
+        self.last_battery = Instant::now();
+        let before = self.battery.level();
+        self.battery.push(self.battery_sense.read_mv().await);
+        let voltage = self.battery.voltage().unwrap_or_default();
+        match self.battery.level() {
+            BatteryLevel::Critical => {
+                warn!("battery empty: {} mV", voltage);
+                if let Some(display) = self.display.as_mut() {
+                    display.set_display_on(true)?;
+                    draw_text(display, "Battery empty")?;
+                    Timer::after(Duration::from_secs(2)).await;
+                }
+                self.power_off().await;
+            }
+            BatteryLevel::Low if before == BatteryLevel::Normal => {
+                warn!("battery low: {} mV", voltage);
+            }
+            _ => {}
+        }
+        if self.menu.is_none() && self.battery.percent() != self.shown_battery {
+            self.refresh().await?;
+        }
+        Ok(())
+    }
+
+    /// 关闭功放、收音芯片和屏幕后进入深度睡眠，按旋钮按键或下一个闹钟到时重新启动
+    ///
+    /// 重新启动后从 RTC 内存恢复频率、音量和静音状态
+    async fn power_off(&mut self) {
+        info!("power off");
+        if let Err(e) = self.save() {
+            error!("{}", e);
+        }
+        let resume = Resume {
+            frequency: self.settings.frequency,
+            volume: self.settings.volume,
+            muted: self.tuner.is_muted(),
+        };
+        self.amplifier.set_standby(true).await;
+        // 功放已经关闭，出错也继续睡眠
+        self.tuner.power_down().await.ok();
+        if let Some(display) = self.display.as_mut() {
+            display.set_display_on(false).ok();
+        }
+        let next = self.clock.and_then(|clock| {
+            alarm::next_alarm(&self.settings.alarms, clock.now(self.rtc.get_time_ms()))
+        });
+        Retained::store(self.clock, next.map(|(index, _)| index), Some(resume));
+        if let Some((index, wait)) = next {
+            info!("alarm {} in {} s", index + 1, wait / 1000);
+        }
+        let timer = next.map(|(_, wait)| core::time::Duration::from_millis(wait as u64));
+        board::sleep_deep(&mut self.rtc, timer)
+    }
+
+    /// 按没有输入的时间变暗、显示屏保或关闭屏幕
+    fn poll_screen(&mut self) -> Result<(), Error> {
+        let idle = self.last_input.elapsed();
+        let state = self.settings.screen.state(idle);
+        let step = (idle.as_secs() / SAVER_MOVE.as_secs()) as u32;
+        let moved = state == ScreenState::Saver && step != self.saver_step;
+        if state == self.screen && !moved {
+            return Ok(());
+        }
+        self.screen = state;
+        self.saver_step = step;
+        let Some(display) = self.display.as_mut() else {
+            return Ok(());
+        };
+        match state {
+            ScreenState::On => {
+                display.set_brightness(brightness(self.settings.display.contrast))?
+            }
+            ScreenState::Dimmed => display.set_brightness(Brightness::DIMMEST)?,
+            ScreenState::Saver => {
+                let time = self
+                    .clock
+                    .map(|clock| clock.time_of_day(self.rtc.get_time_ms()));
+                ui::draw_screensaver(display, time, self.settings.frequency, step)?;
+                display.flush()?;
+                display.clear(BinaryColor::Off)?;
+            }
+            ScreenState::Off => display.set_display_on(false)?,
+        }
+        Ok(())
+    }
+
+    /// 有输入时恢复屏幕，返回屏幕原来是否已经变暗或关闭
+    async fn wake_screen(&mut self) -> Result<bool, Error> {
+        self.last_input = Instant::now();
+        let previous = core::mem::replace(&mut self.screen, ScreenState::On);
+        if previous == ScreenState::On {
+            return Ok(false);
+        }
+        if let Some(display) = self.display.as_mut() {
+            display.set_brightness(brightness(self.settings.display.contrast))?;
+            display.set_display_on(true)?;
+        }
+        // 屏保和关闭时没有绘制界面，需要重新绘制
+        if !previous.shows_content() {
+            if self.menu.is_some() {
+                self.draw_menu().await?;
+            } else {
+                self.refresh().await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// 用 RDS 时间校准时钟，同时保存到 RTC 内存
+    fn set_clock(&mut self, weekday: u8, minutes: u16) {
+        self.clock = Some(Clock::new(self.rtc.get_time_ms(), weekday, minutes));
+        // 校准后时钟可能跳变，从新的时间开始检查闹钟
+        self.alarm_checked = None;
+        Retained::store(self.clock, None, None);
+    }
+
+    /// 检查闹钟是否到时间，响铃时逐渐调大音量，响够时长后关机
+    async fn poll_alarm(&mut self) -> Result<(), Error> {
+        match self.alarm {
+            Some(Wake::Ringing { index, since }) => {
+                let alarm = self.settings.alarms[index];
+                if since.elapsed() >= alarm.duration() {
+                    info!("alarm {} timed out", index + 1);
+                    self.alarm = None;
+                    self.power_off().await;
+                }
+                let volume = alarm.volume(self.settings.volume, since.elapsed());
+                if volume != self.tuner.volume() {
+                    self.tuner.set_volume(volume).await?;
+                    self.refresh().await?;
+                }
+            }
+            Some(Wake::Snoozed { index, until }) if Instant::now() >= until => {
+                return self.ring(index).await;
+            }
+            _ => {}
+        }
+        let Some(clock) = self.clock else {
+            return Ok(());
+        };
+        let now = clock.now(self.rtc.get_time_ms());
+        if let Some(since) = self.alarm_checked.replace(now) {
+            let elapsed = (now + WEEK_MS - since) % WEEK_MS;
+            let due = alarm::due(&self.settings.alarms, since, elapsed);
+            // 正在响铃或贪睡时不再重新响铃
+            if let (None, Some(index)) = (self.alarm, due) {
+                return self.ring(index).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// 闹钟响铃：切换到闹钟的预设电台，从开始音量渐强
+    async fn ring(&mut self, index: usize) -> Result<(), Error> {
+        info!("alarm {}", index + 1);
+        let alarm = self.settings.alarms[index];
+        self.alarm = Some(Wake::Ringing {
+            index,
+            since: Instant::now(),
+        });
+        self.sleep.cancel();
+        self.menu = None;
+        self.wake_screen().await?;
+        self.tuner.set_volume(alarm.volume).await?;
+        if let Some(preset) = self.settings.preset(alarm.preset) {
+            let frequency = preset.frequency;
+            self.tune(frequency).await?;
+        }
+        self.tuner.set_mute(false).await?;
+        self.amplifier.set_muted(false).await;
+        self.refresh().await
+    }
+
+    /// 响铃时长按关闭闹钟继续收听，其他输入贪睡；贪睡期间只响应长按
+    async fn alarm_event(&mut self, wake: Wake, event: InputEvent) -> Result<(), Error> {
+        if event.kind == EventType::KeyLongStart {
+            info!("alarm dismissed");
+            self.alarm = None;
+            self.tuner.set_volume(self.settings.volume).await?;
+            self.tuner.set_mute(false).await?;
+            self.amplifier.set_muted(false).await;
+            return self.refresh().await;
+        }
+        if let Wake::Ringing { index, .. } = wake {
+            info!("alarm snoozed");
+            self.alarm = Some(Wake::Snoozed {
+                index,
+                until: Instant::now() + SNOOZE,
+            });
+            self.amplifier.set_muted(true).await;
+            self.tuner.set_mute(true).await?;
+            return self.refresh().await;
+        }
+        Ok(())
+    }
+
+    /// 没有输入时定时调用
+    async fn poll(&mut self) -> Result<(), Error> {
+        self.poll_screen()?;
+        #[cfg(feature = "battery")]
+        self.poll_battery().await?;
+        if self.sleep.is_active() {
+            self.poll_sleep().await?;
+        }
+        self.poll_alarm().await?;
+        if self.telemetry && self.last_report.elapsed() >= STATUS_PERIOD {
+            self.last_report = Instant::now();
+            self.report_status().await?;
+        }
+        if let Some(menu) = self.menu {
+            if menu.page() == Page::Seek {
+                self.draw_menu().await?;
+            }
+            return Ok(());
+        }
+        let changed = self.station.poll(&mut self.tuner).await?;
+        if let Some((weekday, minutes)) = self.station.take_time() {
+            self.set_clock(weekday, minutes);
+        }
+        if changed {
+            if let Some(record) = self.station.record() {
+                self.report(record);
+            }
+            self.refresh().await?;
+        }
+        Ok(())
+    }
+
+    /// 下次调用 [`Radio::poll`] 的间隔，None 表示只等待输入
+    fn poll_period(&self) -> Option<Duration> {
+        let mut period = match self.menu {
+            // 搜台设置页定时刷新信号强度，主界面定时读取 RDS
+            Some(menu) if menu.page() == Page::Seek => Some(GAUGE_REFRESH),
+            Some(_) => None,
+            #[cfg(feature = "rds")]
+            None => Some(RDS_POLL),
+            #[cfg(not(feature = "rds"))]
+            None => None,
+        };
+        if self.telemetry {
+            period = shortest(period, STATUS_PERIOD);
+        }
+        if self.sleep.is_active() {
+            period = shortest(period, SLEEP_POLL);
+        }
+        let alarm_set = self.settings.alarms.iter().any(|alarm| alarm.enabled);
+        if self.alarm.is_some() || (alarm_set && self.clock.is_some()) {
+            period = shortest(period, ALARM_POLL);
+        }
+        #[cfg(feature = "battery")]
+        {
+            period = shortest(period, BATTERY_POLL);
+        }
+        if self.display.is_some() {
+            if self.screen == ScreenState::Saver {
+                period = shortest(period, SAVER_MOVE);
+            }
+            let idle = self.last_input.elapsed();
+            if let Some(change) = self.settings.screen.next_change(idle) {
+                period = shortest(period, change);
+            }
+        }
+        period
+    }
+
+    async fn handle_event(&mut self, event: InputEvent) -> Result<(), Error> {
+        let event = if self.settings.display.knob_reversed() {
+            InputEvent {
+                kind: event.kind.reversed(),
+                ..event
+            }
+        } else {
+            event
+        };
+        self.report(telemetry::input(&event));
+        let woken = self.wake_screen().await?;
+        if let Some(wake) = self.alarm {
+            return self.alarm_event(wake, event).await;
+        }
+        if self.sleep.is_fading(Instant::now()) {
+            // 渐弱时按任意键取消定时并恢复音量，这次输入不再执行
+            self.sleep.cancel();
+            self.tuner.set_volume(self.settings.volume).await?;
+            info!("sleep timer cancelled");
+            return self.refresh().await;
+        }
+        // 唤醒屏幕的输入不执行动作
+        if woken {
+            return Ok(());
+        }
+        let Some(menu) = self.menu.as_mut() else {
+            let action = self.settings.keymap.lookup(&event);
+            return self.perform(action, event.multiplier).await;
+        };
+        let Some(menu_input) = MenuInput::from_event(&event) else {
+            return Ok(());
+        };
+        match menu.handle(menu_input, event.multiplier, &mut self.settings) {
+            MenuResult::Closed => {
+                self.menu = None;
+                self.save()?;
+                self.refresh().await
+            }
+            MenuResult::Sleep(minutes) => {
+                self.menu = None;
+                self.sleep.set(minutes, Instant::now());
+                info!("sleep timer: {} min", minutes);
+                self.save()?;
+                self.refresh().await
+            }
+            MenuResult::Standby => {
+                self.menu = None;
+                self.power_off().await;
+                Ok(())
+            }
+            result => {
+                if result == MenuResult::Changed {
+                    // 修改后立即生效
+                    self.tuner.set_audio(&self.settings.audio).await?;
+                    self.tuner
+                        .set_seek_threshold(self.settings.seek_threshold)
+                        .await?;
+                    self.amplifier.set_speaker(self.settings.speaker).await;
+                    self.apply_display()?;
+                }
+                self.draw_menu().await
+            }
+        }
+    }
+
+    /// 执行按键映射的动作，`multiplier` 是旋钮加速后的步长倍数
+    async fn perform(&mut self, action: Action, multiplier: u32) -> Result<(), Error> {
+        match action {
+            Action::SeekUp => self.seek(true).await?,
+            Action::SeekDown => self.seek(false).await?,
+            action @ (Action::SeekThresholdUp | Action::SeekThresholdDown) => {
+                let threshold = if action == Action::SeekThresholdUp {
+                    self.settings
+                        .seek_threshold
+                        .saturating_add(1)
+                        .min(MAX_SEEK_THRESHOLD)
+                } else {
+                    self.settings.seek_threshold.saturating_sub(1)
+                };
+                self.tuner.set_seek_threshold(threshold).await?;
+                self.settings.seek_threshold = threshold;
+                self.refresh().await?;
+            }
+            action @ (Action::VolumeUp | Action::VolumeDown) => {
+                if action == Action::VolumeUp {
+                    self.tuner.volume_up().await?;
+                } else {
+                    self.tuner.volume_down().await?;
+                }
+                self.settings.volume = self.tuner.volume();
+                self.refresh().await?;
+            }
+            Action::ToggleMute => {
+                // 静音前先关功放，取消静音后再打开
+                let muted = !self.tuner.is_muted();
+                if muted {
+                    self.amplifier.set_muted(true).await;
+                }
+                let result = self.tuner.set_mute(muted).await;
+                self.amplifier.set_muted(self.tuner.is_muted()).await;
+                result?;
+                info!("mute: {}", self.tuner.is_muted());
+                self.refresh().await?;
+            }
+            action @ (Action::TuneUp | Action::TuneDown) => {
+                let step = 100 * multiplier;
+                let mut freq = self.settings.frequency;
+                // 超出波段时回到另一端
+                if action == Action::TuneUp {
+                    freq += step;
+                    if freq > MAX_FREQUENCY {
+                        freq = MIN_FREQUENCY;
+                    }
+                } else {
+                    freq = freq.saturating_sub(step);
+                    if freq < MIN_FREQUENCY {
+                        freq = MAX_FREQUENCY;
+                    }
+                }
+                self.tune(freq).await?;
+                self.refresh().await?;
+            }
+            Action::PresetRecall(n) => {
+                if let Some(preset) = self.settings.preset(n) {
+                    self.tune(preset.frequency).await?;
+                    self.refresh().await?;
+                }
+            }
+            Action::PresetSave(n) => {
+                self.settings.presets[n as usize] = self.station.preset(self.settings.frequency);
+                self.save()?;
+                info!("save preset {}", n + 1);
+            }
+            Action::Refresh => {
+                // 刷新并显示状态
+                self.refresh().await?;
+            }
+            Action::OpenMenu => {
+                self.menu = Some(Menu::new());
+                self.draw_menu().await?;
+            }
+            Action::Standby => self.power_off().await,
+            Action::None => {}
+        }
+        Ok(())
+    }
+
+    /// 执行串口命令，结果输出到串口
+    #[cfg(feature = "console")]
+    async fn command(&mut self, command: Command) -> Result<(), Error> {
+        match command {
+            Command::Help => println!("{}", console::HELP),
+            Command::Tune(freq) => {
+                self.tune(freq).await?;
+                self.refresh().await?;
+            }
+            Command::Seek(up) => self.seek(up).await?,
+            Command::Volume(volume) => self.set_volume(volume).await?,
+            Command::PresetSave(n) => self.perform(Action::PresetSave(n), 1).await?,
+            Command::PresetRecall(n) => {
+                if self.settings.preset(n).is_none() {
+                    println!("preset {} is empty", n + 1);
+                }
+                self.perform(Action::PresetRecall(n), 1).await?;
+            }
+            Command::PresetList => {
+                for n in 0..self.settings.presets.len() {
+                    match self.settings.preset(n as u8) {
+                        Some(preset) => {
+                            println!("{}: {} kHz {}", n + 1, preset.frequency, preset.name())
+                        }
+                        None => println!("{}: -", n + 1),
+                    }
+                }
+            }
+            Command::PresetExport => {
+                println!("{}", preset::HEADER);
+                for (n, preset) in self.settings.presets.iter().enumerate() {
+                    if !preset.is_empty() {
+                        let slot = n as u8 + 1;
+                        println!(
+                            "{}",
+                            Entry {
+                                slot,
+                                preset: *preset
+                            }
+                        );
+                    }
+                }
+            }
+            Command::PresetImport(n, preset) => {
+                self.settings.presets[n as usize] = preset;
+                self.save()?;
+                println!("import preset {} success!", n + 1);
+            }
+            Command::RdsDump => {
+                #[cfg(feature = "rds")]
+                println!(
+                    "pi: {:?}, pty: {} {}, ps: {:?}",
+                    self.station.rds.pi(),
+                    self.station.rds.pty(),
+                    rds::pty_label(self.station.rds.pty()),
+                    self.station.program_service()
+                );
+                #[cfg(not(feature = "rds"))]
+                println!("rds disabled");
+            }
+            Command::Status => {
+                let status = self.tuner.status().await?;
+                let rssi = self.tuner.rssi().await?;
+                println!(
+                    "freq: {} kHz, rssi: {}, stereo: {}, volume: {}, muted: {}, seek threshold: {}",
+                    self.tuner.read_frequency().await?,
+                    rssi,
+                    status.st,
+                    self.tuner.volume(),
+                    self.tuner.is_muted(),
+                    self.tuner.seek_threshold()
+                );
+            }
+            Command::Telemetry(on) => {
+                self.telemetry = on;
+                self.last_report = Instant::now();
+                self.report(Record::Hello);
+            }
+            Command::Standby => {
+                println!("standby");
+                self.power_off().await;
+            }
+            Command::Reboot => {
+                println!("reboot");
+                self.amplifier.set_standby(true).await;
+                software_reset();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "console")]
+static COMMANDS: CommandChannel = Channel::new();
+
+#[cfg(feature = "console")]
+#[embassy_executor::task]
+async fn console_task(usb_device: USB_DEVICE) {
+    // 输出统一用 esp_println，只用这里的接收
+    let (_, rx) = UsbSerialJtag::new_async(usb_device).split();
+    console::console_run(&COMMANDS, rx).await
+}
+
+#[cfg(feature = "console")]
+async fn next_command() -> Command {
+    COMMANDS.receive().await
+}
+
+#[cfg(not(feature = "console"))]
+async fn next_command() -> Infallible {
+    pending().await
+}
+
+fn display_rotation(options: &DisplayOptions) -> DisplayRotation {
+    if options.flipped {
+        DisplayRotation::Rotate180
+    } else {
+        DisplayRotation::Rotate0
+    }
+}
+
+/// 对比度档位对应的亮度，第 3 档是驱动的默认亮度
+fn brightness(contrast: u8) -> Brightness {
+    match contrast {
+        1 => Brightness::DIMMEST,
+        2 => Brightness::DIM,
+        3 => Brightness::NORMAL,
+        4 => Brightness::BRIGHT,
+        _ => Brightness::BRIGHTEST,
+    }
+}
+
+/// 取两个间隔中较短的一个
+fn shortest(period: Option<Duration>, limit: Duration) -> Option<Duration> {
+    Some(period.map_or(limit, |period| period.min(limit)))
+}
+
+async fn tick(period: Option<Duration>) {
+    match period {
+        Some(period) => Timer::after(period).await,
+        None => pending().await,
+    }
+}
+
+#[embassy_executor::task]
+async fn display_run(
+    mut i2c: I2C<'static, I2C0, Blocking>,
+    amplifier: Amplifier<AmplifierPin>,
+    rtc: Rtc<'static>,
+    #[cfg(feature = "battery")] mut battery_sense: BatterySense,
+) {
+    let retained = Retained::load();
+    // 检查设备是否在线，缺少设备时降级运行
+    let scan = BusScan::scan(&mut i2c);
+    for address in scan.addresses() {
+        info!("i2c device at {:#04x}", address);
+    }
+    if !scan.tuner() {
+        error!("rda5807m not found");
+    }
+    let i2c_bus_manager = BusManagerSimple::new(i2c);
+    // rda5807m
+    let rda5807m = Rda5708m::new(i2c_bus_manager.acquire_i2c(), Address::default());
+    let tuner = Tuner::new(
+        rda5807m,
+        i2c_bus_manager.acquire_i2c(),
+        RetryPolicy::default(),
+    );
+
+    // 恢复上次保存的设置，没有 storage feature 时每次都用默认设置
+    #[cfg(feature = "storage")]
+    let mut storage = SettingsStorage::new(FlashStorage::new());
+    #[cfg(feature = "storage")]
+    let mut settings = storage.load().unwrap_or_default();
+    #[cfg(not(feature = "storage"))]
+    let mut settings = Settings::default();
+
+    // ssd1306 display
+    let display = match scan.display() {
+        Some(address) => {
+            let interface = if address == DISPLAY_ADDRESSES[0] {
+                I2CDisplayInterface::new(i2c_bus_manager.acquire_i2c())
+            } else {
+                I2CDisplayInterface::new_alternate_address(i2c_bus_manager.acquire_i2c())
+            };
+            let rotation = display_rotation(&settings.display);
+            let mut display =
+                Ssd1306::new(interface, DisplaySize128x64, rotation).into_buffered_graphics_mode();
+            if let Err(e) = display.init() {
+                error!("init display: {:?}", e);
+            }
+            Some(display)
+        }
+        None => {
+            warn!("ssd1306 not found, running without display");
+            None
+        }
+    };
+
+    // 从待机或睡眠中醒来时恢复睡眠前的状态
+    let resume = retained.resume();
+    if let Some(resume) = resume {
+        settings.frequency = resume.frequency;
+        settings.volume = resume.volume;
+    }
+    // 先采样一次，开机就显示电量
+    #[cfg(feature = "battery")]
+    let mut battery = Battery::new(battery_sense.config);
+    #[cfg(feature = "battery")]
+    battery.push(battery_sense.read_mv().await);
+    let mut radio = Radio {
+        tuner,
+        display,
+        amplifier,
+        station: Station::default(),
+        settings,
+        menu: None,
+        #[cfg(feature = "storage")]
+        storage,
+        telemetry: false,
+        last_report: Instant::now(),
+        sleep: SleepTimer::new(),
+        shown_sleep: None,
+        rtc,
+        clock: retained.clock(),
+        alarm_checked: None,
+        alarm: None,
+        last_input: Instant::now(),
+        screen: ScreenState::On,
+        saver_step: 0,
+        #[cfg(feature = "battery")]
+        battery_sense,
+        #[cfg(feature = "battery")]
+        battery,
+        #[cfg(feature = "battery")]
+        last_battery: Instant::now(),
+        #[cfg(feature = "battery")]
+        shown_battery: None,
+    };
+    match radio.start().await {
+        Ok(_) => info!("rda5807m started"),
+        Err(e) => radio.show_error(e),
+    }
+    if resume.is_some_and(|resume| resume.muted) {
+        if let Err(e) = radio.perform(Action::ToggleMute, 1).await {
+            radio.show_error(e);
+        }
+    }
+    // 只恢复一次，之后复位按正常启动处理
+    Retained::store(radio.clock, None, None);
+    // 定时器唤醒说明睡眠前设置的闹钟到了
+    if let (SleepSource::Timer, Some(index)) = (get_wakeup_cause(), retained.alarm()) {
+        if let Err(e) = radio.ring(index).await {
+            radio.show_error(e);
+        }
+    }
+
+    let mut subscriber = INPUT_BUS.subscriber().unwrap();
+    loop {
+        let period = radio.poll_period();
+        let result = match select3(subscriber.next(), next_command(), tick(period)).await {
+            Either3::First(event) => radio.handle_event(event).await,
+            #[cfg(feature = "console")]
+            Either3::Second(command) => {
+                let result = radio.command(command).await;
+                // 命令行工具以 ok 或 error 判断一条命令结束
+                match &result {
+                    Ok(_) => println!("ok"),
+                    Err(e) => println!("error: {}", e),
+                }
+                result
+            }
+            #[cfg(not(feature = "console"))]
+            Either3::Second(never) => match never {},
+            Either3::Third(_) => radio.poll().await,
+        };
+        if let Err(e) = result {
+            radio.show_error(e);
+        }
+    }
+}
+
+#[main]
+async fn main(spawner: Spawner) {
+    esp_println::logger::init_logger_from_env();
+    let board = Board::init(Peripherals::take());
+    info!("start");
+    match board.bus_state {
+        BusState::Idle => {}
+        BusState::Recovered => warn!("i2c bus recovered"),
+        BusState::Stuck => error!("i2c bus stuck, SDA held low"),
+    }
+    let amplifier = Amplifier::new(board.amplifier, AmplifierConfig::default());
+    let ec11 = Ec11::new(
+        board.ec11_a,
+        board.ec11_b,
+        board.ec11_key,
+        Ec11Config {
+            acceleration: KNOB_ACCELERATION,
+            ..Ec11Config::default()
+        },
+    );
+    // start
+    #[cfg(feature = "battery")]
+    let display_task = display_run(board.i2c, amplifier, board.rtc, board.battery);
+    #[cfg(not(feature = "battery"))]
+    let display_task = display_run(board.i2c, amplifier, board.rtc);
+    spawner.spawn(display_task).ok();
+    spawner.spawn(sw1_run(board.sw1)).ok();
+    spawner.spawn(sw2_run(board.sw2)).ok();
+    spawner.spawn(sw3_run(board.sw3)).ok();
+    spawner.spawn(ec11_run(ec11)).ok();
+    #[cfg(feature = "console")]
+    spawner.spawn(console_task(board.usb_device)).ok();
+}
@@ -0,0 +1,210 @@
+use core::time::Duration;
+
+#[cfg(feature = "battery")]
+use embassy_time::Timer;
+#[cfg(feature = "battery")]
+use esp_hal::analog::adc::{Adc, AdcCalCurve, AdcConfig, AdcPin, Attenuation};
+use esp_hal::clock::{ClockControl, Clocks};
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{Input, Io, Level, Output, Pull, RtcPinWithResistors};
+use esp_hal::i2c::I2C;
+#[cfg(feature = "battery")]
+use esp_hal::peripherals::ADC1;
+use esp_hal::peripherals::{Peripherals, I2C0, USB_DEVICE};
+use esp_hal::prelude::*;
+use esp_hal::rtc_cntl::sleep::{RtcioWakeupSource, TimerWakeupSource, WakeupLevel};
+use esp_hal::rtc_cntl::Rtc;
+use esp_hal::system::SystemControl;
+use esp_hal::timer::timg::TimerGroup;
+use esp_hal::timer::{ErasedTimer, OneShotTimer};
+use esp_hal::Blocking;
+use static_cell::StaticCell;
+
+#[cfg(feature = "battery")]
+use crate::battery::BatteryConfig;
+use crate::bus::{self, BusState};
+
+#[cfg(not(any(feature = "board-v1", feature = "board-v2")))]
+compile_error!("需要选择一个硬件版本，例如 `--features board-v1`");
+#[cfg(all(feature = "board-v1", feature = "board-v2"))]
+compile_error!("只能选择一个硬件版本");
+#[cfg(all(feature = "battery", not(feature = "board-v2")))]
+compile_error!("`battery` 需要有电池检测电路的硬件版本，例如 `board-v2`");
+
+// 立创开源广场 "收音机 v1" 的引脚分配
+#[cfg(feature = "board-v1")]
+mod pins {
+    use esp_hal::gpio::GpioPin;
+
+    pub type AmplifierPin = GpioPin<0>;
+    pub type Ec11KeyPin = GpioPin<1>;
+    pub type SclPin = GpioPin<2>;
+    pub type SdaPin = GpioPin<3>;
+    pub type Ec11APin = GpioPin<4>;
+    pub type Ec11BPin = GpioPin<5>;
+    pub type Sw2Pin = GpioPin<6>;
+    pub type Sw1Pin = GpioPin<7>;
+    pub type Sw3Pin = GpioPin<9>;
+}
+
+// v1 的基础上把功放开关移到 GPIO10，GPIO0 接电池分压
+#[cfg(feature = "board-v2")]
+mod pins {
+    use esp_hal::gpio::GpioPin;
+
+    use crate::battery::BatteryConfig;
+
+    pub type BatteryPin = GpioPin<0>;
+    pub type Ec11KeyPin = GpioPin<1>;
+    pub type SclPin = GpioPin<2>;
+    pub type SdaPin = GpioPin<3>;
+    pub type Ec11APin = GpioPin<4>;
+    pub type Ec11BPin = GpioPin<5>;
+    pub type Sw2Pin = GpioPin<6>;
+    pub type Sw1Pin = GpioPin<7>;
+    pub type Sw3Pin = GpioPin<9>;
+    pub type AmplifierPin = GpioPin<10>;
+
+    /// 两个 100k 电阻分压，满电 4.2V 时 ADC 引脚 2.1V
+    pub const BATTERY: BatteryConfig = BatteryConfig {
+        upper: 100_000,
+        lower: 100_000,
+        warning: 10,
+        cutoff: 3300,
+    };
+}
+
+pub use pins::*;
+
+/// 堆的大小，格式化显示文字时使用
+pub const HEAP_SIZE: usize = 60 * 1024;
+
+#[global_allocator]
+static ALLOCATOR: embedded_alloc::Heap = embedded_alloc::Heap::empty();
+static ONE_SHOT_TIMER: StaticCell<[OneShotTimer<ErasedTimer>; 1]> = StaticCell::new();
+
+/// 板上所有外设，按用途命名
+pub struct Board {
+    pub clocks: Clocks<'static>,
+    /// 喇叭功放开关，低电平打开，初始为高电平(关闭)
+    pub amplifier: Output<'static, AmplifierPin>,
+    pub sw1: Input<'static, Sw1Pin>,
+    pub sw2: Input<'static, Sw2Pin>,
+    pub sw3: Input<'static, Sw3Pin>,
+    pub ec11_a: Input<'static, Ec11APin>,
+    pub ec11_b: Input<'static, Ec11BPin>,
+    pub ec11_key: Input<'static, Ec11KeyPin>,
+    /// RDA5807M 和 SSD1306 共用的总线
+    pub i2c: I2C<'static, I2C0, Blocking>,
+    /// 初始化 I2C 前总线的状态
+    pub bus_state: BusState,
+    /// USB 串口/JTAG
+    pub usb_device: USB_DEVICE,
+    /// 进入睡眠时使用
+    pub rtc: Rtc<'static>,
+    #[cfg(feature = "battery")]
+    pub battery: BatterySense,
+}
+
+impl Board {
+    /// 初始化堆、时钟、embassy 定时器和 I2C，只能调用一次
+    pub fn init(peripherals: Peripherals) -> Board {
+        init_heap();
+        let system = SystemControl::new(peripherals.SYSTEM);
+        let clocks = ClockControl::boot_defaults(system.clock_control).freeze();
+
+        // embassy 使用的定时器
+        let timer_group = TimerGroup::new(peripherals.TIMG0, &clocks, None);
+        let one_shot_timer = OneShotTimer::new(timer_group.timer0.into());
+        let timers_ref = ONE_SHOT_TIMER.init([one_shot_timer]);
+        esp_hal_embassy::init(&clocks, timers_ref);
+
+        let io = Io::new(peripherals.GPIO, peripherals.IO_MUX);
+        let mut scl: SclPin = io.pins.gpio2;
+        let mut sda: SdaPin = io.pins.gpio3;
+        // 上次复位时从设备可能还拉着 SDA
+        let bus_state = bus::recover(&mut scl, &mut sda, &Delay::new(&clocks));
+        let i2c = I2C::new(peripherals.I2C0, sda, scl, 400.kHz(), &clocks, None);
+        #[cfg(feature = "board-v1")]
+        let amplifier: AmplifierPin = io.pins.gpio0;
+        #[cfg(feature = "board-v2")]
+        let amplifier: AmplifierPin = io.pins.gpio10;
+        #[cfg(feature = "battery")]
+        let battery = BatterySense::new(peripherals.ADC1, io.pins.gpio0);
+
+        Board {
+            amplifier: Output::new(amplifier, Level::High),
+            sw1: Input::new(io.pins.gpio7, Pull::Up),
+            sw2: Input::new(io.pins.gpio6, Pull::Up),
+            sw3: Input::new(io.pins.gpio9, Pull::Up),
+            ec11_a: Input::new(io.pins.gpio4, Pull::Up),
+            ec11_b: Input::new(io.pins.gpio5, Pull::Up),
+            ec11_key: Input::new(io.pins.gpio1, Pull::Up),
+            i2c,
+            bus_state,
+            usb_device: peripherals.USB_DEVICE,
+            rtc: Rtc::new(peripherals.LPWR, None),
+            #[cfg(feature = "battery")]
+            battery,
+            clocks,
+        }
+    }
+}
+
+/// 通过 ADC 读取电池分压后的电压
+#[cfg(feature = "battery")]
+pub struct BatterySense {
+    adc: Adc<'static, ADC1>,
+    pin: AdcPin<BatteryPin, ADC1, AdcCalCurve<ADC1>>,
+    pub config: BatteryConfig,
+}
+
+#[cfg(feature = "battery")]
+impl BatterySense {
+    fn new(adc: ADC1, pin: BatteryPin) -> Self {
+        let mut config = AdcConfig::new();
+        // 11dB 衰减时量程约 2.5V
+        let pin = config.enable_pin_with_cal(pin, Attenuation::Attenuation11dB);
+        BatterySense {
+            adc: Adc::new(adc, config),
+            pin,
+            config: BATTERY,
+        }
+    }
+
+    /// 读取一次 ADC 引脚上的电压(mV)，已经校准
+    ///
+    /// 转换需要几十微秒，等待期间让出 CPU
+    pub async fn read_mv(&mut self) -> u16 {
+        loop {
+            if let Ok(mv) = self.adc.read_oneshot(&mut self.pin) {
+                return mv;
+            }
+            Timer::after(embassy_time::Duration::from_micros(20)).await;
+        }
+    }
+}
+
+/// 进入深度睡眠，按下旋钮按键或经过 `timer` 后从头启动
+///
+/// C3 只有 GPIO0～5 能唤醒深度睡眠，SW1～SW3 不能唤醒
+pub fn sleep_deep(rtc: &mut Rtc, timer: Option<Duration>) -> ! {
+    // 按键引脚已交给输入任务，睡眠前不会再读取，这里只用来配置唤醒
+    let mut key = unsafe { Ec11KeyPin::steal() };
+    let mut pins: [(&mut dyn RtcPinWithResistors, WakeupLevel); 1] = [(&mut key, WakeupLevel::Low)];
+    let wakeup = RtcioWakeupSource::new(&mut pins);
+    match timer {
+        Some(duration) => rtc.sleep_deep(&[&wakeup, &TimerWakeupSource::new(duration)]),
+        None => rtc.sleep_deep(&[&wakeup]),
+    }
+}
+
+fn init_heap() {
+    static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+    unsafe {
+        ALLOCATOR.init(
+            core::ptr::addr_of_mut!(HEAP) as usize,
+            core::mem::size_of::<[u8; HEAP_SIZE]>(),
+        )
+    };
+}
@@ -0,0 +1,112 @@
+use embedded_hal_02::blocking::i2c::Write;
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{InputPin, Level, OutputOpenDrain, OutputPin, Pull};
+use esp_hal::peripheral::Peripheral;
+
+/// RDA5807M 随机访问模式的地址
+pub const TUNER_ADDRESS: u8 = 0x11;
+/// SSD1306 的地址由 SA0 决定
+pub const DISPLAY_ADDRESSES: [u8; 2] = [0x3C, 0x3D];
+/// 7 位地址中可用的范围，其余为保留地址
+const SCAN_FIRST: u8 = 0x08;
+const SCAN_LAST: u8 = 0x77;
+/// 释放总线最多需要的时钟数
+const CLEAR_PULSES: u8 = 9;
+/// 100kHz 的半个周期
+const HALF_PERIOD_US: u32 = 5;
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum BusState {
+    /// SDA 本来就是高电平
+    Idle,
+    /// 有设备拉低 SDA，发送时钟后已经释放
+    Recovered,
+    /// 发送时钟后 SDA 仍然是低电平
+    Stuck,
+}
+
+/// 在初始化 I2C 外设前调用，从设备在传输中途复位时可能一直拉低 SDA
+///
+/// 以开漏方式发送最多 9 个 SCL 时钟，让从设备把剩下的数据位移出，然后产生 STOP
+pub fn recover<SCL, SDA>(
+    scl: impl Peripheral<P = SCL>,
+    sda: impl Peripheral<P = SDA>,
+    delay: &Delay,
+) -> BusState
+where
+    SCL: InputPin + OutputPin,
+    SDA: InputPin + OutputPin,
+{
+    let mut scl = OutputOpenDrain::new(scl, Level::High, Pull::Up);
+    let mut sda = OutputOpenDrain::new(sda, Level::High, Pull::Up);
+    delay.delay_micros(HALF_PERIOD_US);
+    if sda.is_high() {
+        return BusState::Idle;
+    }
+    for _ in 0..CLEAR_PULSES {
+        scl.set_low();
+        delay.delay_micros(HALF_PERIOD_US);
+        scl.set_high();
+        delay.delay_micros(HALF_PERIOD_US);
+        if sda.is_high() {
+            break;
+        }
+    }
+    // STOP：SCL 为高时 SDA 从低变高
+    scl.set_low();
+    delay.delay_micros(HALF_PERIOD_US);
+    sda.set_low();
+    delay.delay_micros(HALF_PERIOD_US);
+    scl.set_high();
+    delay.delay_micros(HALF_PERIOD_US);
+    sda.set_high();
+    delay.delay_micros(HALF_PERIOD_US);
+    if sda.is_high() {
+        BusState::Recovered
+    } else {
+        BusState::Stuck
+    }
+}
+
+/// 总线扫描结果，每个地址一位
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+pub struct BusScan {
+    found: [u8; 16],
+}
+
+impl BusScan {
+    /// 向每个地址发送空的写操作，有应答的记为存在
+    pub fn scan<I>(i2c: &mut I) -> Self
+    where
+        I: Write,
+    {
+        let mut scan = BusScan::default();
+        for address in SCAN_FIRST..=SCAN_LAST {
+            if i2c.write(address, &[]).is_ok() {
+                scan.found[address as usize / 8] |= 1 << (address % 8);
+            }
+        }
+        scan
+    }
+
+    pub fn contains(&self, address: u8) -> bool {
+        address < 0x80 && self.found[address as usize / 8] & (1 << (address % 8)) != 0
+    }
+
+    pub fn tuner(&self) -> bool {
+        self.contains(TUNER_ADDRESS)
+    }
+
+    /// 屏幕实际使用的地址
+    pub fn display(&self) -> Option<u8> {
+        DISPLAY_ADDRESSES
+            .iter()
+            .copied()
+            .find(|address| self.contains(*address))
+    }
+
+    /// 所有有应答的地址
+    pub fn addresses(&self) -> impl Iterator<Item = u8> + '_ {
+        (SCAN_FIRST..=SCAN_LAST).filter(|address| self.contains(*address))
+    }
+}
@@ -0,0 +1,52 @@
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embedded_io_async::Read;
+use esp_println::print;
+
+pub use crate::command::{
+    parse, Command, LineBuffer, ParseError, HELP, LINE_LEN, MAX_FREQUENCY, MIN_FREQUENCY,
+};
+
+/// 等待执行的命令数
+pub const COMMAND_QUEUE: usize = 4;
+
+pub type CommandChannel = Channel<CriticalSectionRawMutex, Command, COMMAND_QUEUE>;
+
+/// 从串口读取命令，解析后发送到 `commands`，命令的结果由接收方输出，
+/// 最后一行是 `ok` 或 `error: ...`
+///
+/// 输入的字符会回显，解析失败时直接输出错误。回显和其他输出一样通过
+/// `esp_println` 写到同一个串口，不会和命令的结果交错在一行里
+pub async fn console_run<R>(commands: &CommandChannel, mut rx: R) -> !
+where
+    R: Read,
+{
+    let mut line = LineBuffer::new();
+    let mut buf = [0; 16];
+    let mut previous = 0;
+    loop {
+        let Ok(n) = rx.read(&mut buf).await else {
+            continue;
+        };
+        for &byte in &buf[..n] {
+            // 回车换行只算一次
+            let crlf = previous == b'\r' && byte == b'\n';
+            previous = byte;
+            if crlf {
+                continue;
+            }
+            match byte {
+                b'\r' | b'\n' => print!("\r\n"),
+                0x08 | 0x7F => print!("\x08 \x08"),
+                // 非 ASCII 字节不回显
+                _ if byte.is_ascii() => print!("{}", byte as char),
+                _ => {}
+            }
+            match line.push(byte) {
+                Some(Ok(command)) => commands.send(command).await,
+                Some(Err(e)) => print!("error: {}\r\n", e),
+                None => {}
+            }
+        }
+    }
+}
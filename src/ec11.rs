@@ -1,156 +1,334 @@
 use embassy_futures::select::{select, Either};
-use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::mutex::Mutex;
-use embassy_time::Instant;
+use embassy_time::{Duration, Instant, Ticker, Timer};
 use esp_hal::gpio::{Input, InputPin};
 
+use crate::acceleration::AccelerationProfile;
 use crate::ec11::WheelDirection::{Back, Front, NoState};
-use crate::event::{key_detection, EventType};
+use crate::event::{EventType, KeyState};
+pub use crate::quadrature::WheelDirection;
+use crate::quadrature::{QuadratureDecoder, StepMode};
+pub use esp32c3_fm_encoder::rotation::RotateState;
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-pub enum WheelDirection {
-    Front,
-    Back,
-    NoState,
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Ec11Config {
+    /// 同方向两次转动间隔超过该时间(ms)则重新计算速度
+    pub speed_delay: u64,
+    /// 每个定位点对应的 A/B 状态变化次数
+    pub step_mode: StepMode,
+    /// 转动速度到步长倍数的换算
+    pub acceleration: AccelerationProfile,
 }
 
-//转动时判断方向是否一致，
-//一致则判断last_time是否过久，过久则重记时间
-//方向一致且时间不过久则步长加1 ，更新 last_time ,并用 步数 / 时间得到速度，
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-pub struct RotateState {
-    pub begin_timestamp: u64,
-    pub last_timestamp: u64,
-    pub wheel_direction: WheelDirection,
-    pub steps: u32,
+impl Default for Ec11Config {
+    fn default() -> Self {
+        Ec11Config {
+            speed_delay: 300,
+            step_mode: StepMode::Full,
+            acceleration: AccelerationProfile::None,
+        }
+    }
 }
 
-impl RotateState {
-    const fn new() -> Self {
-        RotateState {
-            begin_timestamp: 0,
-            last_timestamp: 0,
-            wheel_direction: NoState,
-            steps: 0,
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Ec11Event {
+    pub event_type: EventType,
+    /// 转动速度(步/秒)，按键事件为 0
+    pub speed: f32,
+    /// 按加速曲线换算出的步长倍数，按键事件为 1
+    pub multiplier: u32,
+}
+
+/// [`EdgeBackend`] 默认的毛刺滤波时间
+pub const DEFAULT_GLITCH_FILTER: Duration = Duration::from_micros(200);
+/// [`SampledBackend`] 默认的采样周期，可以跟上每秒 1000 次以上的状态变化
+pub const DEFAULT_SAMPLE_PERIOD: Duration = Duration::from_micros(250);
+
+/// A/B 相电平来源，决定如何发现编码器的状态变化
+#[allow(async_fn_in_trait)]
+pub trait Ec11Backend {
+    /// 当前 A/B 电平 `(a_is_high, b_is_high)`
+    fn levels(&self) -> (bool, bool);
+
+    /// 等待 A/B 电平发生变化，返回变化后的电平
+    ///
+    /// 必须可以随时取消：[`Ec11::next_event`] 按住按键时会和定时器一起 select，
+    /// 返回前被丢弃的等待在下次调用时继续，不能丢掉已经发生的变化
+    async fn wait_for_change(&mut self) -> (bool, bool);
+}
+
+/// GPIO 中断触发，A/B 和上次记录的电平不同时等待 `glitch_filter` 再读取电平，
+/// 期间的抖动被忽略，读到的电平未变化视为毛刺
+///
+/// 使用电平中断而不是边沿中断，调用者处理上一个变化期间发生的变化不会丢失
+pub struct EdgeBackend<P1, P2>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
+{
+    a_point: Input<'static, P1>,
+    b_point: Input<'static, P2>,
+    glitch_filter: Duration,
+    levels: (bool, bool),
+    /// 正在滤波时为滤波结束的时间，等待被取消后下次从这里继续
+    settle_at: Option<Instant>,
+}
+
+impl<P1, P2> EdgeBackend<P1, P2>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
+{
+    pub fn new(
+        a_point: Input<'static, P1>,
+        b_point: Input<'static, P2>,
+        glitch_filter: Duration,
+    ) -> Self {
+        let levels = (a_point.is_high(), b_point.is_high());
+        EdgeBackend {
+            a_point,
+            b_point,
+            glitch_filter,
+            levels,
+            settle_at: None,
         }
     }
+}
+
+impl<P1, P2> Ec11Backend for EdgeBackend<P1, P2>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
+{
+    fn levels(&self) -> (bool, bool) {
+        (self.a_point.is_high(), self.b_point.is_high())
+    }
 
-    fn do_step(&mut self, wheel_direction: WheelDirection) {
-        const SPEED_DELAY: u64 = 300;
-        let ms = Instant::now().as_millis();
-        if self.wheel_direction == wheel_direction {
-            if ms - self.last_timestamp < SPEED_DELAY {
-                self.last_timestamp = ms;
-                self.steps += 1;
-                return;
+    async fn wait_for_change(&mut self) -> (bool, bool) {
+        loop {
+            let settle_at = match self.settle_at {
+                Some(settle_at) => settle_at,
+                None => {
+                    let (a_is_high, b_is_high) = self.levels;
+                    let a_change = wait_for_level(&mut self.a_point, !a_is_high);
+                    let b_change = wait_for_level(&mut self.b_point, !b_is_high);
+                    select(a_change, b_change).await;
+                    let settle_at = Instant::now() + self.glitch_filter;
+                    self.settle_at = Some(settle_at);
+                    settle_at
+                }
+            };
+            Timer::at(settle_at).await;
+            self.settle_at = None;
+            let levels = self.levels();
+            if levels != self.levels {
+                self.levels = levels;
+                return levels;
             }
         }
-        self.wheel_direction = wheel_direction;
-        self.begin_timestamp = ms;
-        self.last_timestamp = ms;
-        self.steps = 1;
-    }
-
-    pub fn speed(&self) -> f32 {
-        if self.steps > 3 {
-            let speed =
-                self.steps as f32 / (self.last_timestamp - self.begin_timestamp) as f32 * 1000.0;
-            // println!("begin_time:{:?}", self.begin_timestamp);
-            // println!("last_time:{:?}", self.last_timestamp);
-            // println!("steps:{:?}", self.steps);
-            // println!("speed:{:?}", speed);
-            return speed;
-        } else {
-            return self.steps as f32;
+    }
+}
+
+/// 等待引脚变为指定电平，已经是该电平时立即返回
+async fn wait_for_level<P: InputPin + 'static>(pin: &mut Input<'static, P>, high: bool) {
+    if high {
+        pin.wait_for_high().await
+    } else {
+        pin.wait_for_low().await
+    }
+}
+
+/// 定时器按固定周期采样 A/B，周期小于最快转动时相邻两次变化的间隔即不会丢步，
+/// 不依赖 GPIO 中断
+pub struct SampledBackend<P1, P2>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
+{
+    a_point: Input<'static, P1>,
+    b_point: Input<'static, P2>,
+    ticker: Ticker,
+    levels: (bool, bool),
+}
+
+impl<P1, P2> SampledBackend<P1, P2>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
+{
+    pub fn new(a_point: Input<'static, P1>, b_point: Input<'static, P2>, period: Duration) -> Self {
+        let levels = (a_point.is_high(), b_point.is_high());
+        SampledBackend {
+            a_point,
+            b_point,
+            ticker: Ticker::every(period),
+            levels,
         }
     }
 }
 
-static ROTATE_STATE: Mutex<CriticalSectionRawMutex, RotateState> = Mutex::new(RotateState::new());
-
-const SAMPLE_TIMES: u32 = 10;
-const JUDGE_TIMES: u32 = 8;
-
-pub async fn ec11_detection<P1, P2, P3, F>(
-    a_point: &mut Input<'static, P1>,
-    b_point: &mut Input<'static, P2>,
-    push_key: &mut Input<'static, P3>,
-    mut callback: F,
-) where
-    P1: InputPin,
-    P2: InputPin,
-    P3: InputPin,
-    F: FnMut(EventType, f32) -> (),
+impl<P1, P2> Ec11Backend for SampledBackend<P1, P2>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
 {
-    // 初始化编码器状态
-    let mut begin_state = NoState;
-
-    // 开始监听编码器状态变化
-    loop {
-        let a_edge = a_point.wait_for_any_edge();
-        let key_edge = push_key.wait_for_falling_edge();
-
-        match select(a_edge, key_edge).await {
-            Either::First(_) => {
-                let mut a_is_low_times = 0;
-                let mut b_is_low_times = 0;
-                for _i in 0..SAMPLE_TIMES {
-                    if a_point.is_low() {
-                        a_is_low_times += 1;
-                    }
-                    if b_point.is_low() {
-                        b_is_low_times += 1;
-                    }
-                }
+    fn levels(&self) -> (bool, bool) {
+        (self.a_point.is_high(), self.b_point.is_high())
+    }
 
-                let mut a_is_down = false;
-                let mut b_is_down = false;
-                if a_is_low_times > JUDGE_TIMES {
-                    a_is_down = true;
-                } else if a_is_low_times < SAMPLE_TIMES - JUDGE_TIMES {
-                    a_is_down = false;
-                } else {
-                    continue;
-                }
-                if b_is_low_times > JUDGE_TIMES {
-                    b_is_down = true;
-                } else if b_is_low_times < SAMPLE_TIMES - JUDGE_TIMES {
-                    b_is_down = false;
-                } else {
-                    continue;
-                }
-                //下降沿开始
-                if a_is_down {
-                    if b_is_down {
-                        begin_state = Front;
-                        continue;
-                    } else if !b_is_down {
-                        begin_state = Back;
-                        continue;
-                    }
-                    begin_state = NoState;
-                } else {
-                    //上升沿判断结束
-                    if !b_is_down {
-                        if begin_state == Front {
-                            let mut rotate_state = ROTATE_STATE.lock().await;
-                            rotate_state.do_step(Front);
-                            callback(EventType::EC11Front, rotate_state.speed());
+    async fn wait_for_change(&mut self) -> (bool, bool) {
+        loop {
+            self.ticker.next().await;
+            let levels = self.levels();
+            if levels != self.levels {
+                self.levels = levels;
+                return levels;
+            }
+        }
+    }
+}
+
+/// EC11 旋钮编码器，每个实例拥有自己的引脚和转动状态，
+/// 多个编码器可以分别在各自的 task 中调用 [`Ec11::next_event`]
+pub struct Ec11<B, P>
+where
+    P: InputPin + 'static,
+{
+    backend: B,
+    push_key: Input<'static, P>,
+    config: Ec11Config,
+    rotate_state: RotateState,
+    decoder: QuadratureDecoder,
+    key_state: Option<KeyState>,
+}
+
+impl<P1, P2, P3> Ec11<EdgeBackend<P1, P2>, P3>
+where
+    P1: InputPin + 'static,
+    P2: InputPin + 'static,
+    P3: InputPin + 'static,
+{
+    /// 使用 GPIO 中断方式检测 A/B
+    pub fn new(
+        a_point: Input<'static, P1>,
+        b_point: Input<'static, P2>,
+        push_key: Input<'static, P3>,
+        config: Ec11Config,
+    ) -> Self {
+        let backend = EdgeBackend::new(a_point, b_point, DEFAULT_GLITCH_FILTER);
+        Self::with_backend(backend, push_key, config)
+    }
+}
+
+impl<B, P> Ec11<B, P>
+where
+    B: Ec11Backend,
+    P: InputPin + 'static,
+{
+    pub fn with_backend(backend: B, push_key: Input<'static, P>, config: Ec11Config) -> Self {
+        let mut decoder = QuadratureDecoder::new(config.step_mode);
+        let (a_is_high, b_is_high) = backend.levels();
+        decoder.reset(a_is_high, b_is_high);
+        Ec11 {
+            backend,
+            push_key,
+            config,
+            rotate_state: RotateState::new(),
+            decoder,
+            key_state: None,
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn rotate_state(&self) -> &RotateState {
+        &self.rotate_state
+    }
+
+    pub fn decoder(&self) -> &QuadratureDecoder {
+        &self.decoder
+    }
+
+    /// 等待下一个转动或按键事件，按住按键时转动产生
+    /// [`EventType::EC11PressedFront`] / [`EventType::EC11PressedBack`]
+    pub async fn next_event(&mut self) -> Ec11Event {
+        loop {
+            if self.key_state.is_some() {
+                // 按键按下期间每 1ms 检测一次，直到释放，同时检测转动，
+                // 定时器先到时被丢弃的 wait_for_change 下次继续
+                let change = self.backend.wait_for_change();
+                let tick = Timer::after(Duration::from_millis(1));
+                match select(change, tick).await {
+                    Either::First((a_is_high, b_is_high)) => {
+                        if let Some(event) = self.detect_rotation(a_is_high, b_is_high) {
+                            return event;
                         }
-                    } else if b_is_down {
-                        if begin_state == Back {
-                            let mut rotate_state = ROTATE_STATE.lock().await;
-                            rotate_state.do_step(Back);
-                            rotate_state.speed();
-                            callback(EventType::EC11Back, rotate_state.speed());
+                    }
+                    Either::Second(_) => {
+                        if let Some(event) = self.detect_key() {
+                            return event;
                         }
                     }
-                    begin_state = NoState;
                 }
+                continue;
             }
-            Either::Second(_) => {
-                key_detection(push_key, |event_type| callback(event_type, 0.0)).await;
+
+            let change = self.backend.wait_for_change();
+            let key_edge = self.push_key.wait_for_falling_edge();
+
+            match select(change, key_edge).await {
+                Either::First((a_is_high, b_is_high)) => {
+                    if let Some(event) = self.detect_rotation(a_is_high, b_is_high) {
+                        return event;
+                    }
+                }
+                Either::Second(_) => {
+                    self.key_state = Some(KeyState::new());
+                }
             }
         }
     }
+
+    fn detect_key(&mut self) -> Option<Ec11Event> {
+        let key_state = self.key_state.as_mut()?;
+        let event_type = key_state.sample(&self.push_key)?;
+        if event_type.is_key_end() {
+            self.key_state = None;
+        }
+        Some(Ec11Event {
+            event_type,
+            speed: 0.0,
+            multiplier: 1,
+        })
+    }
+
+    fn detect_rotation(&mut self, a_is_high: bool, b_is_high: bool) -> Option<Ec11Event> {
+        let wheel_direction = self.decoder.update(a_is_high, b_is_high)?;
+        let pressed = match self.key_state.as_mut() {
+            Some(key_state) => {
+                key_state.mark_rotated();
+                true
+            }
+            None => false,
+        };
+        let event_type = match (wheel_direction, pressed) {
+            (Front, false) => EventType::EC11Front,
+            (Back, false) => EventType::EC11Back,
+            (Front, true) => EventType::EC11PressedFront,
+            (Back, true) => EventType::EC11PressedBack,
+            (NoState, _) => return None,
+        };
+        self.rotate_state.do_step(
+            wheel_direction,
+            Instant::now().as_millis(),
+            self.config.speed_delay,
+        );
+        let speed = self.rotate_state.speed();
+        Some(Ec11Event {
+            event_type,
+            speed,
+            multiplier: self.config.acceleration.multiplier(speed),
+        })
+    }
 }
@@ -0,0 +1,102 @@
+use core::fmt;
+
+use display_interface::DisplayError;
+use embassy_time::Duration;
+
+/// I2C 通信失败的原因
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum I2cFault {
+    /// 设备没有应答
+    Nack,
+    Timeout,
+    /// 总线被占用或 SDA 被拉低
+    Bus,
+    Other,
+}
+
+impl I2cFault {
+    /// 没有应答和超时通常是偶发的，可以重试
+    pub fn is_transient(&self) -> bool {
+        matches!(self, I2cFault::Nack | I2cFault::Timeout)
+    }
+}
+
+impl From<esp_hal::i2c::Error> for I2cFault {
+    fn from(value: esp_hal::i2c::Error) -> Self {
+        match value {
+            esp_hal::i2c::Error::AckCheckFailed => I2cFault::Nack,
+            esp_hal::i2c::Error::TimeOut => I2cFault::Timeout,
+            esp_hal::i2c::Error::ArbitrationLost => I2cFault::Bus,
+            _ => I2cFault::Other,
+        }
+    }
+}
+
+/// 应用层统一的错误
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Error {
+    /// 收音机芯片通信失败
+    Tuner(I2cFault),
+    /// 屏幕通信失败
+    Display,
+    /// 设置读写失败
+    Storage,
+}
+
+impl Error {
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Tuner(fault) => fault.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+impl<E> From<rda5807m::Error<E>> for Error
+where
+    E: Into<I2cFault>,
+{
+    fn from(value: rda5807m::Error<E>) -> Self {
+        match value {
+            rda5807m::Error::I2C(e) => Error::Tuner(e.into()),
+        }
+    }
+}
+
+impl From<DisplayError> for Error {
+    fn from(_: DisplayError) -> Self {
+        Error::Display
+    }
+}
+
+/// 错误页面上显示的文字
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Tuner(fault) => write!(f, "Tuner {:?}", fault),
+            Error::Display => write!(f, "Display"),
+            Error::Storage => write!(f, "Storage"),
+        }
+    }
+}
+
+/// 偶发错误的重试策略
+#[derive(Debug, Copy, Clone)]
+pub struct RetryPolicy {
+    /// 每次操作最多尝试的次数
+    pub attempts: u8,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub backoff: Duration,
+    /// 连续失败多少次操作后重新初始化芯片
+    pub reinit_after: u8,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(2),
+            reinit_after: 3,
+        }
+    }
+}
@@ -1,24 +1,35 @@
 use embassy_time::{Duration, Instant, Timer};
 use esp_hal::gpio::{Input, InputPin};
 
-#[derive(Eq, PartialEq, Debug)]
-pub enum EventType {
-    KeyShort,
-    KeyLongStart,
-    KeyLongIng,
-    KeyLongEnd,
-    EC11Front,
-    EC11Back,
+pub use esp32c3_fm_settings::event::EventType;
+
+/// 单次按键的检测状态，按键按下后每 1ms 调用一次 [`KeyState::sample`]
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct KeyState {
+    begin_ms: u64,
+    is_long: bool,
+    rotated: bool,
 }
 
-pub async fn key_detection<P, F>(key: &Input<'static, P>, mut callback: F)
-where
-    P: InputPin,
-    F: FnMut(EventType) -> (),
-{
-    let begin_ms = Instant::now().as_millis();
-    let mut is_long = false;
-    loop {
+impl KeyState {
+    pub fn new() -> Self {
+        KeyState {
+            begin_ms: Instant::now().as_millis(),
+            is_long: false,
+            rotated: false,
+        }
+    }
+
+    /// 按下期间旋钮发生了转动，之后不再触发短按，也不会开始长按，
+    /// 松开时产生 [`EventType::EC11PressedEnd`]
+    pub fn mark_rotated(&mut self) {
+        self.rotated = true;
+    }
+
+    pub fn sample<P>(&mut self, key: &Input<'static, P>) -> Option<EventType>
+    where
+        P: InputPin,
+    {
         let mut is_low_times = 0;
         for _i in 0..100 {
             if key.is_low() {
@@ -27,28 +38,53 @@ where
         }
         if is_low_times > 80 {
             //按下
+            if self.rotated && !self.is_long {
+                return None;
+            }
             let current = Instant::now().as_millis();
-            if current - begin_ms > 500 {
+            if current - self.begin_ms > 500 {
                 //长时间按下
-                if !is_long {
-                    is_long = true;
-                    callback(EventType::KeyLongStart);
+                if !self.is_long {
+                    self.is_long = true;
+                    return Some(EventType::KeyLongStart);
                 } else {
-                    callback(EventType::KeyLongIng);
+                    return Some(EventType::KeyLongIng);
                 }
             }
         } else if is_low_times < 2 {
             //释放
-            if is_long {
+            if self.is_long {
                 //长时间按下后释放
-                callback(EventType::KeyLongEnd);
-                return;
+                return Some(EventType::KeyLongEnd);
+            } else if self.rotated {
+                //按住转动后释放
+                return Some(EventType::EC11PressedEnd);
             } else {
                 //短时按下，等几ms 看是否有下一次按下，如有则是双击
-                loop {
-                    callback(EventType::KeyShort);
-                    return;
-                }
+                return Some(EventType::KeyShort);
+            }
+        }
+        None
+    }
+}
+
+impl Default for KeyState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub async fn key_detection<P, F>(key: &Input<'static, P>, mut callback: F)
+where
+    P: InputPin,
+    F: FnMut(EventType),
+{
+    let mut key_state = KeyState::new();
+    loop {
+        if let Some(event_type) = key_state.sample(key) {
+            callback(event_type);
+            if event_type.is_key_end() {
+                return;
             }
         }
         Timer::after(Duration::from_millis(1)).await;
@@ -0,0 +1,147 @@
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::pubsub::{Error, PubSubChannel, Subscriber, WaitResult};
+use esp_hal::gpio::{Input, InputPin};
+
+use crate::ec11::{Ec11, Ec11Backend};
+use crate::event::key_detection;
+
+pub use esp32c3_fm_settings::input::{InputEvent, InputId};
+
+/// 事件队列长度
+pub const INPUT_BUS_CAP: usize = 32;
+/// 最多同时订阅的数量(界面、日志、电源管理…)
+pub const INPUT_BUS_SUBS: usize = 4;
+/// 发布统一使用 immediate publisher，不占用 publisher 名额
+pub const INPUT_BUS_PUBS: usize = 1;
+
+pub type InputChannel = PubSubChannel<
+    CriticalSectionRawMutex,
+    InputEvent,
+    INPUT_BUS_CAP,
+    INPUT_BUS_SUBS,
+    INPUT_BUS_PUBS,
+>;
+
+/// 输入事件总线，支持多个订阅者，并统计丢失的事件
+pub struct InputBus {
+    channel: InputChannel,
+    overflows: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+    lagged: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+}
+
+impl InputBus {
+    pub const fn new() -> Self {
+        InputBus {
+            channel: PubSubChannel::new(),
+            overflows: Mutex::new(Cell::new(0)),
+            lagged: Mutex::new(Cell::new(0)),
+        }
+    }
+
+    /// 发布事件，不会阻塞输入检测。
+    /// 队列满时挤掉最旧的事件，并记入 [`InputBus::overflows`]
+    pub fn publish(&self, event: InputEvent) {
+        let publisher = self.channel.immediate_publisher();
+        if let Err(event) = publisher.try_publish(event) {
+            Self::increase(&self.overflows, 1);
+            publisher.publish_immediate(event);
+        }
+    }
+
+    pub fn subscriber(&self) -> Result<InputSubscriber<'_>, Error> {
+        Ok(InputSubscriber {
+            bus: self,
+            subscriber: self.channel.subscriber()?,
+        })
+    }
+
+    /// 队列满时被挤掉的事件数
+    pub fn overflows(&self) -> u32 {
+        self.overflows.lock(|overflows| overflows.get())
+    }
+
+    /// 所有订阅者因处理不及时错过的事件数
+    pub fn lagged(&self) -> u32 {
+        self.lagged.lock(|lagged| lagged.get())
+    }
+
+    fn increase(counter: &Mutex<CriticalSectionRawMutex, Cell<u32>>, n: u32) {
+        counter.lock(|counter| counter.set(counter.get().wrapping_add(n)));
+    }
+}
+
+impl Default for InputBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct InputSubscriber<'a> {
+    bus: &'a InputBus,
+    subscriber: Subscriber<
+        'a,
+        CriticalSectionRawMutex,
+        InputEvent,
+        INPUT_BUS_CAP,
+        INPUT_BUS_SUBS,
+        INPUT_BUS_PUBS,
+    >,
+}
+
+impl<'a> InputSubscriber<'a> {
+    pub async fn next(&mut self) -> InputEvent {
+        loop {
+            match self.subscriber.next_message().await {
+                WaitResult::Message(event) => return event,
+                WaitResult::Lagged(n) => {
+                    InputBus::increase(&self.bus.lagged, n.min(u32::MAX as u64) as u32)
+                }
+            }
+        }
+    }
+
+    pub fn try_next(&mut self) -> Option<InputEvent> {
+        loop {
+            match self.subscriber.try_next_message()? {
+                WaitResult::Message(event) => return Some(event),
+                WaitResult::Lagged(n) => {
+                    InputBus::increase(&self.bus.lagged, n.min(u32::MAX as u64) as u32)
+                }
+            }
+        }
+    }
+}
+
+/// 检测按键并把事件发布到总线
+pub async fn key_run<P>(bus: &InputBus, source: InputId, mut key: Input<'static, P>) -> !
+where
+    P: InputPin,
+{
+    loop {
+        key.wait_for_falling_edge().await;
+        key_detection(&key, |event_type| {
+            bus.publish(InputEvent::key(source, event_type));
+        })
+        .await;
+    }
+}
+
+/// 检测旋钮并把事件发布到总线
+pub async fn ec11_run<B, P>(bus: &InputBus, source: InputId, mut ec11: Ec11<B, P>) -> !
+where
+    B: Ec11Backend,
+    P: InputPin + 'static,
+{
+    loop {
+        let event = ec11.next_event().await;
+        bus.publish(InputEvent {
+            source,
+            kind: event.event_type,
+            velocity: event.speed,
+            multiplier: event.multiplier,
+        });
+    }
+}
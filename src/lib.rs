@@ -1,5 +1,31 @@
 #![no_std]
 #![no_main]
 
+pub use esp32c3_fm_encoder::acceleration;
+pub use esp32c3_fm_settings::action;
+pub use esp32c3_fm_settings::alarm;
+pub mod amplifier;
+#[cfg(feature = "battery")]
+pub use esp32c3_fm_battery as battery;
+pub mod board;
+pub mod bus;
+pub use esp32c3_fm_command as command;
+#[cfg(feature = "console")]
+pub mod console;
 pub mod ec11;
+pub mod error;
 pub mod event;
+pub mod input;
+pub mod menu;
+pub use esp32c3_fm_encoder::quadrature;
+pub use esp32c3_fm_preset as preset;
+#[cfg(feature = "rds")]
+pub mod rds;
+pub use esp32c3_fm_settings::screen;
+pub use esp32c3_fm_settings::settings;
+pub mod sleep;
+#[cfg(feature = "storage")]
+pub mod storage;
+pub mod telemetry;
+pub mod tuner;
+pub mod ui;
@@ -0,0 +1,493 @@
+use core::fmt::{self, Write};
+
+use crate::action::{Action, KEYMAP_LEN, PRESET_COUNT};
+use crate::alarm::{Alarm, ALARM_COUNT, MAX_DURATION, MAX_RAMP};
+use crate::event::EventType;
+use crate::input::{InputEvent, InputId};
+use crate::screen::{step_timeout, CONTRAST_LEVELS};
+use crate::settings::Settings;
+use crate::sleep::SLEEP_MINUTES;
+use crate::tuner::{MAX_SEEK_THRESHOLD, MAX_VOLUME};
+
+/// 128x64 屏幕使用 6x10 字体时可以显示的行数
+pub const MENU_LINES: usize = 6;
+
+/// 菜单中的导航操作，由旋钮事件转换而来
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum MenuInput {
+    Next,
+    Prev,
+    Select,
+    Back,
+}
+
+impl MenuInput {
+    /// 菜单打开时旋钮固定用于导航，不经过按键映射
+    pub fn from_event(event: &InputEvent) -> Option<MenuInput> {
+        if event.source != InputId::Ec11 {
+            return None;
+        }
+        match event.kind {
+            EventType::EC11Front => Some(MenuInput::Next),
+            EventType::EC11Back => Some(MenuInput::Prev),
+            EventType::KeyShort => Some(MenuInput::Select),
+            EventType::KeyLongStart => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Page {
+    Root,
+    Audio,
+    Seek,
+    Keys,
+    Display,
+    /// 闹钟编号从 0 开始
+    Alarm(u8),
+    Sleep,
+    /// 不是子页面，选中后直接待机
+    Standby,
+}
+
+/// 根菜单下的子页面
+const ROOT_PAGES: [Page; 8] = [
+    Page::Audio,
+    Page::Seek,
+    Page::Keys,
+    Page::Display,
+    Page::Alarm(0),
+    Page::Alarm(1),
+    Page::Sleep,
+    Page::Standby,
+];
+
+/// 音频页的设置项
+const AUDIO_ITEMS: [&str; 5] = ["Speaker", "Bass", "Mono", "SoftMute", "Blend"];
+
+/// 屏幕页的设置项，前三项是没有操作多久后变暗、显示屏保、关闭
+const DISPLAY_ITEMS: [&str; 7] = [
+    "Dim", "Saver", "Off", "Rotate", "Contrast", "Invert", "FlipKnob",
+];
+
+/// 闹钟页的设置项
+const ALARM_ITEMS: [&str; 8] = [
+    "Enabled", "Hour", "Minute", "Days", "Preset", "Volume", "Ramp", "Length",
+];
+/// 与 `ROOT_PAGES` 中的闹钟页对应
+const ALARM_TITLES: [&str; ALARM_COUNT] = ["Alarm 1", "Alarm 2"];
+
+impl Page {
+    fn title(&self) -> &'static str {
+        match self {
+            Page::Root => "Settings",
+            Page::Audio => "Audio",
+            Page::Seek => "Seek",
+            Page::Keys => "Keys",
+            Page::Display => "Display",
+            Page::Alarm(n) => ALARM_TITLES[*n as usize],
+            Page::Sleep => "Sleep",
+            Page::Standby => "Standby",
+        }
+    }
+
+    /// 不含最后的返回项
+    fn item_count(&self) -> usize {
+        match self {
+            Page::Root => ROOT_PAGES.len(),
+            Page::Audio => AUDIO_ITEMS.len(),
+            Page::Seek => 1,
+            Page::Keys => KEYMAP_LEN,
+            Page::Display => DISPLAY_ITEMS.len(),
+            Page::Alarm(_) => ALARM_ITEMS.len(),
+            Page::Sleep => SLEEP_MINUTES.len(),
+            Page::Standby => 0,
+        }
+    }
+
+    fn write_item(&self, index: usize, settings: &Settings, out: &mut impl Write) -> fmt::Result {
+        match self {
+            Page::Root => write!(out, "{}", ROOT_PAGES[index].title()),
+            Page::Audio => {
+                write!(
+                    out,
+                    "{} {}",
+                    AUDIO_ITEMS[index],
+                    on_off(audio_value(settings, index))
+                )
+            }
+            Page::Seek => write!(
+                out,
+                "Threshold {}/{}",
+                settings.seek_threshold, MAX_SEEK_THRESHOLD
+            ),
+            Page::Keys => {
+                let binding = &settings.keymap.bindings[index];
+                write!(
+                    out,
+                    "{:?} {} ",
+                    binding.source,
+                    event_type_label(binding.kind)
+                )?;
+                write_action(out, binding.action)
+            }
+            Page::Display => {
+                write!(out, "{} ", DISPLAY_ITEMS[index])?;
+                write_display_value(out, settings, index)
+            }
+            Page::Alarm(n) => {
+                write!(out, "{} ", ALARM_ITEMS[index])?;
+                write_alarm_value(out, &settings.alarms[*n as usize], index)
+            }
+            Page::Sleep => match SLEEP_MINUTES[index] {
+                0 => write!(out, "Off"),
+                minutes => write!(out, "{} min", minutes),
+            },
+            Page::Standby => Ok(()),
+        }
+    }
+
+    /// 数值项随旋钮加速，开关和选项不加速
+    fn accelerates(&self, index: usize) -> bool {
+        match self {
+            Page::Seek => true,
+            // 超时和对比度
+            Page::Display => matches!(index, 0..=2 | 4),
+            // 时间、音量、渐强和响铃时长
+            Page::Alarm(_) => matches!(index, 1 | 2 | 5..=7),
+            _ => false,
+        }
+    }
+
+    /// 编辑当前项，返回设置是否变化
+    fn adjust(&self, index: usize, input: MenuInput, settings: &mut Settings) -> bool {
+        match self {
+            Page::Root | Page::Sleep | Page::Standby => false,
+            Page::Audio => {
+                let value = !audio_value(settings, index);
+                match index {
+                    0 => settings.speaker = value,
+                    1 => settings.audio.bass = value,
+                    2 => settings.audio.mono = value,
+                    3 => settings.audio.soft_mute = value,
+                    _ => settings.audio.soft_blend = value,
+                }
+                true
+            }
+            Page::Seek => {
+                let threshold = match input {
+                    MenuInput::Next => settings.seek_threshold.saturating_add(1),
+                    _ => settings.seek_threshold.saturating_sub(1),
+                }
+                .min(MAX_SEEK_THRESHOLD);
+                let changed = threshold != settings.seek_threshold;
+                settings.seek_threshold = threshold;
+                changed
+            }
+            Page::Keys => {
+                let binding = &mut settings.keymap.bindings[index];
+                binding.action = match input {
+                    MenuInput::Next => binding.action.next(),
+                    _ => binding.action.prev(),
+                };
+                true
+            }
+            Page::Display => {
+                let before = (settings.screen, settings.display);
+                adjust_display(settings, index, input == MenuInput::Next);
+                (settings.screen, settings.display) != before
+            }
+            Page::Alarm(n) => {
+                let alarm = &mut settings.alarms[*n as usize];
+                let before = *alarm;
+                adjust_alarm(alarm, index, input == MenuInput::Next);
+                *alarm != before
+            }
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum MenuResult {
+    None,
+    /// 设置被修改
+    Changed,
+    /// 菜单已关闭
+    Closed,
+    /// 选择了睡眠定时(分钟)，0 表示取消，菜单随之关闭
+    Sleep(u8),
+    /// 选择了待机，菜单随之关闭
+    Standby,
+}
+
+/// 设置菜单，旋钮转动选择，短按进入/编辑，长按返回
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Menu {
+    page: Page,
+    cursor: usize,
+    editing: bool,
+}
+
+impl Menu {
+    pub const fn new() -> Self {
+        Menu {
+            page: Page::Root,
+            cursor: 0,
+            editing: false,
+        }
+    }
+
+    pub fn page(&self) -> Page {
+        self.page
+    }
+
+    /// `multiplier` 是旋钮加速后的步长倍数，快速转动时光标和数值一次移动多步
+    pub fn handle(
+        &mut self,
+        input: MenuInput,
+        multiplier: u32,
+        settings: &mut Settings,
+    ) -> MenuResult {
+        let count = self.page.item_count();
+        let steps = multiplier.max(1) as usize;
+        if self.editing {
+            return match input {
+                MenuInput::Next | MenuInput::Prev => {
+                    // 开关和选项每次只切换一下
+                    let steps = if self.page.accelerates(self.cursor) {
+                        steps
+                    } else {
+                        1
+                    };
+                    let mut changed = false;
+                    for _ in 0..steps {
+                        changed |= self.page.adjust(self.cursor, input, settings);
+                    }
+                    if changed {
+                        MenuResult::Changed
+                    } else {
+                        MenuResult::None
+                    }
+                }
+                MenuInput::Select | MenuInput::Back => {
+                    self.editing = false;
+                    MenuResult::None
+                }
+            };
+        }
+        match input {
+            // 最后一项是返回，单步时首尾循环，加速时停在首尾
+            MenuInput::Next if steps == 1 => self.cursor = (self.cursor + 1) % (count + 1),
+            MenuInput::Prev if steps == 1 => self.cursor = (self.cursor + count) % (count + 1),
+            MenuInput::Next => self.cursor = (self.cursor + steps).min(count),
+            MenuInput::Prev => self.cursor = self.cursor.saturating_sub(steps),
+            MenuInput::Select if self.cursor == count => return self.back(),
+            MenuInput::Select => match self.page {
+                Page::Root if ROOT_PAGES[self.cursor] == Page::Standby => {
+                    *self = Menu::new();
+                    return MenuResult::Standby;
+                }
+                Page::Root => {
+                    self.page = ROOT_PAGES[self.cursor];
+                    self.cursor = 0;
+                }
+                // 选中即生效，不需要编辑
+                Page::Sleep => {
+                    let minutes = SLEEP_MINUTES[self.cursor];
+                    *self = Menu::new();
+                    return MenuResult::Sleep(minutes);
+                }
+                // 打开菜单的绑定固定，不能编辑
+                Page::Keys if settings.keymap.bindings[self.cursor].is_fixed() => {}
+                _ => self.editing = true,
+            },
+            MenuInput::Back => return self.back(),
+        }
+        MenuResult::None
+    }
+
+    fn back(&mut self) -> MenuResult {
+        match self.page {
+            Page::Root => {
+                *self = Menu::new();
+                MenuResult::Closed
+            }
+            page => {
+                self.cursor = ROOT_PAGES.iter().position(|p| *p == page).unwrap_or(0);
+                self.page = Page::Root;
+                MenuResult::None
+            }
+        }
+    }
+
+    /// 标题加当前光标附近的菜单项，每行一项
+    pub fn render(&self, settings: &Settings, out: &mut impl Write) -> fmt::Result {
+        writeln!(out, "{}", self.page.title())?;
+        let count = self.page.item_count() + 1;
+        let rows = MENU_LINES - 1;
+        let first = self
+            .cursor
+            .saturating_sub(rows - 1)
+            .min(count.saturating_sub(rows));
+        for index in first..count.min(first + rows) {
+            let marker = match (index == self.cursor, self.editing) {
+                (true, true) => '*',
+                (true, false) => '>',
+                _ => ' ',
+            };
+            write!(out, "{}", marker)?;
+            if index == count - 1 {
+                let back = if self.page == Page::Root {
+                    "Exit"
+                } else {
+                    "Back"
+                };
+                write!(out, "{}", back)?;
+            } else {
+                self.page.write_item(index, settings, out)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 与 `AUDIO_ITEMS` 一一对应
+fn audio_value(settings: &Settings, index: usize) -> bool {
+    match index {
+        0 => settings.speaker,
+        1 => settings.audio.bass,
+        2 => settings.audio.mono,
+        3 => settings.audio.soft_mute,
+        _ => settings.audio.soft_blend,
+    }
+}
+
+/// 与 `DISPLAY_ITEMS` 一一对应
+fn write_display_value(out: &mut impl Write, settings: &Settings, index: usize) -> fmt::Result {
+    let display = &settings.display;
+    match index {
+        0 => write_timeout(out, settings.screen.dim),
+        1 => write_timeout(out, settings.screen.saver),
+        2 => write_timeout(out, settings.screen.off),
+        3 => write!(out, "{}", if display.flipped { 180 } else { 0 }),
+        4 => write!(out, "{}/{}", display.contrast, CONTRAST_LEVELS),
+        5 => write!(out, "{}", on_off(display.inverted)),
+        _ => write!(out, "{}", on_off(display.flip_knob)),
+    }
+}
+
+fn write_timeout(out: &mut impl Write, seconds: u16) -> fmt::Result {
+    match seconds {
+        0 => write!(out, "never"),
+        seconds if seconds < 60 => write!(out, "{}s", seconds),
+        seconds => write!(out, "{} min", seconds / 60),
+    }
+}
+
+/// 超时和对比度到边界为止，开关项每次切换
+fn adjust_display(settings: &mut Settings, index: usize, up: bool) {
+    let screen = &mut settings.screen;
+    let display = &mut settings.display;
+    match index {
+        0 => screen.dim = step_timeout(screen.dim, up),
+        1 => screen.saver = step_timeout(screen.saver, up),
+        2 => screen.off = step_timeout(screen.off, up),
+        3 => display.flipped = !display.flipped,
+        4 if up => display.contrast = (display.contrast + 1).min(CONTRAST_LEVELS),
+        4 => display.contrast = display.contrast.saturating_sub(1).max(1),
+        5 => display.inverted = !display.inverted,
+        _ => display.flip_knob = !display.flip_knob,
+    }
+}
+
+/// 与 `ALARM_ITEMS` 一一对应
+fn write_alarm_value(out: &mut impl Write, alarm: &Alarm, index: usize) -> fmt::Result {
+    match index {
+        0 => write!(out, "{}", on_off(alarm.enabled)),
+        1 => write!(out, "{:02}", alarm.hour),
+        2 => write!(out, "{:02}", alarm.minute),
+        3 => write!(out, "{}", alarm.days_label()),
+        4 => write!(out, "P{}", alarm.preset + 1),
+        5 => write!(out, "{}/{}", alarm.volume, MAX_VOLUME),
+        6 => write!(out, "{} min", alarm.ramp),
+        _ => write!(out, "{} min", alarm.duration),
+    }
+}
+
+/// 时间和预设循环切换，其余数值到边界为止
+fn adjust_alarm(alarm: &mut Alarm, index: usize, up: bool) {
+    let step = |value: u8, min: u8, max: u8| {
+        if up {
+            value.saturating_add(1).min(max)
+        } else {
+            value.saturating_sub(1).max(min)
+        }
+    };
+    let cycle = |value: u8, count: u8| {
+        if up {
+            (value + 1) % count
+        } else {
+            (value + count - 1) % count
+        }
+    };
+    match index {
+        0 => alarm.enabled = !alarm.enabled,
+        1 => alarm.hour = cycle(alarm.hour, 24),
+        2 => alarm.minute = cycle(alarm.minute, 60),
+        3 => alarm.cycle_days(up),
+        4 => alarm.preset = cycle(alarm.preset, PRESET_COUNT),
+        5 => alarm.volume = step(alarm.volume, 0, MAX_VOLUME),
+        6 => alarm.ramp = step(alarm.ramp, 0, MAX_RAMP),
+        _ => alarm.duration = step(alarm.duration, 1, MAX_DURATION),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn event_type_label(kind: EventType) -> &'static str {
+    match kind {
+        EventType::KeyShort => "Short",
+        EventType::KeyLongStart => "Long",
+        EventType::KeyLongIng => "Hold",
+        EventType::KeyLongEnd => "Up",
+        EventType::EC11Front => "Turn+",
+        EventType::EC11Back => "Turn-",
+        EventType::EC11PressedFront => "Push+",
+        EventType::EC11PressedBack => "Push-",
+        EventType::EC11PressedEnd => "PushUp",
+    }
+}
+
+fn write_action(out: &mut impl Write, action: Action) -> fmt::Result {
+    match action {
+        Action::None => write!(out, "-"),
+        Action::TuneUp => write!(out, "Tune+"),
+        Action::TuneDown => write!(out, "Tune-"),
+        Action::SeekUp => write!(out, "Seek+"),
+        Action::SeekDown => write!(out, "Seek-"),
+        Action::VolumeUp => write!(out, "Vol+"),
+        Action::VolumeDown => write!(out, "Vol-"),
+        Action::ToggleMute => write!(out, "Mute"),
+        Action::SeekThresholdUp => write!(out, "Th+"),
+        Action::SeekThresholdDown => write!(out, "Th-"),
+        Action::PresetRecall(n) => write!(out, "P{}", n + 1),
+        Action::PresetSave(n) => write!(out, "Save P{}", n + 1),
+        Action::Refresh => write!(out, "Refresh"),
+        Action::OpenMenu => write!(out, "Menu"),
+        Action::Standby => write!(out, "Standby"),
+    }
+}
@@ -0,0 +1,213 @@
+/// 节目名(PS)固定 8 个字符
+pub const PS_LEN: usize = 8;
+/// 块错误等级大于这个值的组直接丢弃
+///
+/// 0：没有错误，1：1～2 个错误，2：3～5 个错误，3：无法纠正
+const MAX_BLOCK_ERRORS: u8 = 1;
+
+/// 一组 RDS 数据
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Group {
+    /// A～D 四个块
+    pub blocks: [u16; 4],
+    /// A、B 块的错误等级(BLERA/BLERB)
+    pub block_errors: [u8; 2],
+}
+
+impl Group {
+    /// 组类型 0～15
+    pub fn group_type(&self) -> u8 {
+        (self.blocks[1] >> 12) as u8
+    }
+
+    /// false 为 A 版本，true 为 B 版本
+    pub fn version_b(&self) -> bool {
+        self.blocks[1] & (1 << 11) != 0
+    }
+}
+
+/// 电台播发的时间(CT)，UTC 加本地时区偏移
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct ClockTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    /// UTC 日期是星期几，0 为星期一
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// 本地时间相对 UTC 的偏移，单位半小时
+    pub offset: i8,
+}
+
+impl ClockTime {
+    /// 本地时间一天中的第几分钟
+    pub fn local_minutes(&self) -> u16 {
+        let utc = self.hour as i32 * 60 + self.minute as i32;
+        (utc + self.offset as i32 * 30).rem_euclid(24 * 60) as u16
+    }
+
+    /// 本地时间是星期几，0 为星期一
+    pub fn local_weekday(&self) -> u8 {
+        let utc = self.hour as i32 * 60 + self.minute as i32;
+        let days = (utc + self.offset as i32 * 30).div_euclid(24 * 60);
+        (self.weekday as i32 + days).rem_euclid(7) as u8
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum RdsUpdate {
+    /// 收到完整的节目名
+    ProgramService,
+    ProgramType,
+    ClockTime(ClockTime),
+}
+
+/// RDS 解码，只处理 0A/0B(节目名、节目类型)和 4A(时间)
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Rds {
+    pi: Option<u16>,
+    pty: u8,
+    // 正在接收的节目名，每组 2 个字符
+    pending: [u8; PS_LEN],
+    // 已收到的段，每段一位
+    segments: u8,
+    ps: [u8; PS_LEN],
+    ps_valid: bool,
+}
+
+impl Rds {
+    pub const fn new() -> Self {
+        Rds {
+            pi: None,
+            pty: 0,
+            pending: [b' '; PS_LEN],
+            segments: 0,
+            ps: [b' '; PS_LEN],
+            ps_valid: false,
+        }
+    }
+
+    /// 换台后清空
+    pub fn reset(&mut self) {
+        *self = Rds::new();
+    }
+
+    /// 节目识别码
+    pub fn pi(&self) -> Option<u16> {
+        self.pi
+    }
+
+    pub fn pty(&self) -> u8 {
+        self.pty
+    }
+
+    /// 四段都收到后才有节目名
+    pub fn program_service(&self) -> Option<&str> {
+        if !self.ps_valid {
+            return None;
+        }
+        core::str::from_utf8(&self.ps).ok()
+    }
+
+    pub fn decode(&mut self, group: &Group) -> Option<RdsUpdate> {
+        if group.block_errors.iter().any(|e| *e > MAX_BLOCK_ERRORS) {
+            return None;
+        }
+        let [a, b, c, d] = group.blocks;
+        if self.pi != Some(a) {
+            // 电台变了
+            self.reset();
+            self.pi = Some(a);
+        }
+        let pty = ((b >> 5) & 0x1F) as u8;
+        let pty_changed = pty != self.pty;
+        self.pty = pty;
+        let update = match (group.group_type(), group.version_b()) {
+            (0, _) => self.decode_ps(b, d),
+            (4, false) => decode_clock(b, c, d).map(RdsUpdate::ClockTime),
+            _ => None,
+        };
+        match update {
+            None if pty_changed => Some(RdsUpdate::ProgramType),
+            update => update,
+        }
+    }
+
+    fn decode_ps(&mut self, b: u16, d: u16) -> Option<RdsUpdate> {
+        let segment = (b & 0b11) as usize;
+        self.pending[segment * 2] = ps_char((d >> 8) as u8);
+        self.pending[segment * 2 + 1] = ps_char(d as u8);
+        self.segments |= 1 << segment;
+        if self.segments != 0b1111 {
+            return None;
+        }
+        self.segments = 0;
+        let changed = !self.ps_valid || self.ps != self.pending;
+        self.ps = self.pending;
+        self.ps_valid = true;
+        changed.then_some(RdsUpdate::ProgramService)
+    }
+}
+
+impl Default for Rds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 只保留可显示的 ASCII 字符
+fn ps_char(c: u8) -> u8 {
+    if (0x20..0x7F).contains(&c) {
+        c
+    } else {
+        b'?'
+    }
+}
+
+/// 4A 组：C、D 块中是修正儒略日(MJD)、UTC 时分和时区偏移
+fn decode_clock(b: u16, c: u16, d: u16) -> Option<ClockTime> {
+    let mjd = ((b as u32 & 0b11) << 15) | (c as u32 >> 1);
+    let hour = (((c & 1) << 4) | (d >> 12)) as u8;
+    let minute = ((d >> 6) & 0x3F) as u8;
+    let offset = (d & 0x1F) as i8;
+    let offset = if d & (1 << 5) != 0 { -offset } else { offset };
+    // 没有时间信息时电台发送全 0
+    if mjd < 15079 || hour > 23 || minute > 59 {
+        return None;
+    }
+    let (year, month, day) = mjd_to_date(mjd);
+    Some(ClockTime {
+        year,
+        month,
+        day,
+        // MJD 0 是星期三
+        weekday: ((mjd + 2) % 7) as u8,
+        hour,
+        minute,
+        offset,
+    })
+}
+
+/// EN 50067 附录 G 的换算公式，系数放大后用整数计算
+fn mjd_to_date(mjd: u32) -> (u16, u8, u8) {
+    let y = (mjd * 100 - 1_507_820) / 36_525;
+    let days_y = y * 36_525 / 100;
+    let m = ((mjd - 14_956 - days_y) * 10_000 - 1_000) / 306_001;
+    let day = mjd - 14_956 - days_y - m * 306_001 / 10_000;
+    let k = if m == 14 || m == 15 { 1 } else { 0 };
+    let year = 1900 + y + k;
+    let month = m - 1 - k * 12;
+    (year as u16, month as u8, day as u8)
+}
+
+/// 节目类型的显示名(欧洲 RDS)
+pub fn pty_label(pty: u8) -> &'static str {
+    const LABELS: [&str; 32] = [
+        "None", "News", "Affairs", "Info", "Sport", "Educate", "Drama", "Culture", "Science",
+        "Varied", "Pop M", "Rock M", "Easy M", "Light M", "Classics", "Other M", "Weather",
+        "Finance", "Children", "Social", "Religion", "Phone In", "Travel", "Leisure", "Jazz",
+        "Country", "Nation M", "Oldies", "Folk M", "Document", "TEST", "Alarm !",
+    ];
+    LABELS.get(pty as usize).copied().unwrap_or("")
+}
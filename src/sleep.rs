@@ -0,0 +1,86 @@
+use core::fmt;
+
+use embassy_time::{Duration, Instant};
+
+/// 菜单中可选的定时关机时长(分钟)，0 表示关闭
+pub const SLEEP_MINUTES: [u8; 5] = [0, 15, 30, 60, 90];
+/// 关机前的最后一分钟逐渐降低音量
+pub const FADE_TIME: Duration = Duration::from_secs(60);
+
+/// 屏幕上显示的剩余时间，最后一分钟按秒显示
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Remaining {
+    Minutes(u32),
+    Seconds(u32),
+}
+
+impl fmt::Display for Remaining {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Remaining::Minutes(minutes) => write!(f, "{}m", minutes),
+            Remaining::Seconds(seconds) => write!(f, "{}s", seconds),
+        }
+    }
+}
+
+/// 睡眠定时器，到时间后由调用者关闭收音机
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+pub struct SleepTimer {
+    deadline: Option<Instant>,
+}
+
+impl SleepTimer {
+    pub const fn new() -> Self {
+        SleepTimer { deadline: None }
+    }
+
+    /// 从 `now` 开始计时，`minutes` 为 0 时取消
+    pub fn set(&mut self, minutes: u8, now: Instant) {
+        self.deadline = match minutes {
+            0 => None,
+            minutes => Some(now + Duration::from_secs(minutes as u64 * 60)),
+        };
+    }
+
+    pub fn cancel(&mut self) {
+        self.deadline = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// 是否处于最后一分钟的渐弱阶段
+    pub fn is_fading(&self, now: Instant) -> bool {
+        self.left(now).is_some_and(|left| left < FADE_TIME)
+    }
+
+    /// 剩余时间向上取整，没有定时时为 None
+    pub fn remaining(&self, now: Instant) -> Option<Remaining> {
+        let seconds = self.left(now)?.as_millis().div_ceil(1000) as u32;
+        if seconds > 60 {
+            Some(Remaining::Minutes(seconds.div_ceil(60)))
+        } else {
+            Some(Remaining::Seconds(seconds))
+        }
+    }
+
+    /// 渐弱阶段按剩余时间线性降低 `volume`，其他时候原样返回
+    pub fn volume(&self, volume: u8, now: Instant) -> u8 {
+        match self.left(now) {
+            Some(left) if left < FADE_TIME => {
+                (volume as u64 * left.as_millis()).div_ceil(FADE_TIME.as_millis()) as u8
+            }
+            _ => volume,
+        }
+    }
+
+    fn left(&self, now: Instant) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+}
@@ -0,0 +1,40 @@
+use embedded_storage::{ReadStorage, Storage};
+
+use crate::settings::Settings;
+
+/// 默认分区表中 nvs 分区的位置，固件不使用 esp-idf 的 nvs，直接用来保存设置
+pub const SETTINGS_OFFSET: u32 = 0x9000;
+
+/// 把 [`Settings`] 保存到 flash
+pub struct SettingsStorage<S> {
+    flash: S,
+    offset: u32,
+}
+
+impl<S> SettingsStorage<S>
+where
+    S: Storage,
+{
+    pub fn new(flash: S) -> Self {
+        Self::with_offset(flash, SETTINGS_OFFSET)
+    }
+
+    pub fn with_offset(flash: S, offset: u32) -> Self {
+        SettingsStorage { flash, offset }
+    }
+
+    /// 读取失败或数据无效时返回 `None`
+    pub fn load(&mut self) -> Option<Settings> {
+        let mut buf = [0u8; Settings::ENCODED_LEN];
+        self.flash.read(self.offset, &mut buf).ok()?;
+        Settings::decode(&buf)
+    }
+
+    /// 内容未变化时不写 flash
+    pub fn save(&mut self, settings: &Settings) -> Result<(), <S as ReadStorage>::Error> {
+        if self.load().as_ref() == Some(settings) {
+            return Ok(());
+        }
+        self.flash.write(self.offset, &settings.encode())
+    }
+}
@@ -0,0 +1,42 @@
+use embassy_time::Duration;
+
+use crate::event::EventType;
+use crate::input::{InputEvent, InputId};
+
+/// 格式见 `telemetry/src/lib.rs`，和电脑上的工具共用
+pub use esp32c3_fm_telemetry::{Record, SCHEMA_VERSION};
+
+/// 发送 status 的间隔
+pub const STATUS_PERIOD: Duration = Duration::from_secs(1);
+
+/// 按键和旋钮事件的记录，名称和 [`InputId`]、[`EventType`] 一致
+pub fn input(event: &InputEvent) -> Record<'static> {
+    Record::Input {
+        source: source_name(event.source),
+        kind: kind_name(event.kind),
+        velocity: event.velocity,
+    }
+}
+
+fn source_name(source: InputId) -> &'static str {
+    match source {
+        InputId::Sw1 => "Sw1",
+        InputId::Sw2 => "Sw2",
+        InputId::Sw3 => "Sw3",
+        InputId::Ec11 => "Ec11",
+    }
+}
+
+fn kind_name(kind: EventType) -> &'static str {
+    match kind {
+        EventType::KeyShort => "KeyShort",
+        EventType::KeyLongStart => "KeyLongStart",
+        EventType::KeyLongIng => "KeyLongIng",
+        EventType::KeyLongEnd => "KeyLongEnd",
+        EventType::EC11Front => "EC11Front",
+        EventType::EC11Back => "EC11Back",
+        EventType::EC11PressedFront => "EC11PressedFront",
+        EventType::EC11PressedBack => "EC11PressedBack",
+        EventType::EC11PressedEnd => "EC11PressedEnd",
+    }
+}
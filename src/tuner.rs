@@ -0,0 +1,359 @@
+use embassy_time::{Duration, Timer};
+use embedded_hal_02::blocking::i2c::{Read, Write, WriteRead};
+#[cfg(feature = "rds")]
+use rda5807m::register_address::StatusBitFlag;
+use rda5807m::register_address::{ConfigBitFlags, Register, StatusRegister};
+use rda5807m::Rda5708m;
+
+use crate::error::{Error, I2cFault, RetryPolicy};
+#[cfg(feature = "rds")]
+use crate::rds::Group;
+
+pub use esp32c3_fm_settings::tuner::{AudioConfig, MAX_SEEK_THRESHOLD, MAX_VOLUME};
+
+/// 音量渐变时每一级的间隔，16 级约 150ms
+const RAMP_STEP_DELAY: Duration = Duration::from_millis(10);
+/// 调频后等待锁定的时间
+const TUNE_DELAY: Duration = Duration::from_millis(10);
+
+// 驱动没有导出的寄存器位
+/// 随机访问模式的 I2C 地址，与驱动的默认地址相同
+const RANDOM_ADDRESS: u8 = 0b10001;
+/// 04H: 软静音。0：禁用，1：启用
+const SOFTMUTE_EN: u16 = 1 << 9;
+/// 07H: 立体声软混合。0：禁用，1：启用
+const SOFTBLEND_EN: u16 = 1 << 1;
+/// 0BH: A、B 块的错误等级
+#[cfg(feature = "rds")]
+const BLERA_SHIFT: u16 = 2;
+#[cfg(feature = "rds")]
+const BLER_MASK: u16 = 0b11;
+
+/// 对 RDA5807M 的封装，记录音量和静音状态，静音和换台时音量渐变避免爆音
+///
+/// 通信失败时按 `RetryPolicy` 重试，连续失败后重新初始化芯片并恢复记录的状态
+pub struct Tuner<I2C> {
+    rda5807m: Rda5708m<I2C>,
+    // 同一总线的另一个句柄，用来读写驱动没有封装的寄存器
+    i2c: I2C,
+    policy: RetryPolicy,
+    // 用户设置的音量
+    volume: u8,
+    // 芯片当前的音量，渐变过程中与 volume 不同
+    output: u8,
+    muted: bool,
+    // 芯片当前的输出状态(DMUTE)
+    output_muted: bool,
+    // 最后一次设置的频率(kHz)，0 表示还没有设置
+    frequency: u32,
+    seek_threshold: u8,
+    audio: AudioConfig,
+    // 连续失败的操作次数
+    failures: u8,
+}
+
+impl<I2C, E> Tuner<I2C>
+where
+    I2C: Write<Error = E> + Read<Error = E> + WriteRead<Error = E>,
+    E: Into<I2cFault>,
+{
+    pub fn new(rda5807m: Rda5708m<I2C>, i2c: I2C, policy: RetryPolicy) -> Self {
+        Tuner {
+            rda5807m,
+            i2c,
+            policy,
+            volume: 0,
+            output: 0,
+            muted: false,
+            output_muted: false,
+            frequency: 0,
+            seek_threshold: 8,
+            audio: AudioConfig::default(),
+            failures: 0,
+        }
+    }
+
+    /// 启动芯片并设置初始音量
+    pub async fn start(&mut self, volume: u8) -> Result<(), Error> {
+        self.volume = volume.min(MAX_VOLUME);
+        self.output = self.volume;
+        self.muted = false;
+        self.output_muted = false;
+        self.call(|tuner| {
+            tuner.rda5807m.start()?;
+            tuner.rda5807m.set_volume(tuner.output)
+        })
+        .await
+    }
+
+    /// 未封装的操作直接使用驱动，不会重试
+    pub fn driver(&mut self) -> &mut Rda5708m<I2C> {
+        &mut self.rda5807m
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn seek_threshold(&self) -> u8 {
+        self.seek_threshold
+    }
+
+    /// 最后一次设置或读到的频率(kHz)
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    pub async fn status(&mut self) -> Result<StatusRegister, Error> {
+        self.call(|tuner| tuner.rda5807m.get_status()).await
+    }
+
+    pub async fn rssi(&mut self) -> Result<u8, Error> {
+        self.call(|tuner| tuner.rda5807m.get_rssi()).await
+    }
+
+    /// 从芯片读取当前频率，搜台结束后用来同步
+    pub async fn read_frequency(&mut self) -> Result<u32, Error> {
+        let frequency = self.call(|tuner| tuner.rda5807m.get_frequency()).await?;
+        self.frequency = frequency;
+        Ok(frequency)
+    }
+
+    /// 开始搜台，到达边界后从另一端继续，结束时状态寄存器的 STC 置位
+    pub async fn seek(&mut self, up: bool) -> Result<(), Error> {
+        self.call(|tuner| {
+            if up {
+                tuner.rda5807m.seek_up(true)
+            } else {
+                tuner.rda5807m.seek_down(true)
+            }
+        })
+        .await
+    }
+
+    pub async fn set_seek_threshold(&mut self, threshold: u8) -> Result<(), Error> {
+        let threshold = threshold.min(MAX_SEEK_THRESHOLD);
+        self.call(|tuner| tuner.rda5807m.set_seek_threshold(threshold))
+            .await?;
+        self.seek_threshold = threshold;
+        Ok(())
+    }
+
+    pub async fn set_volume(&mut self, volume: u8) -> Result<(), Error> {
+        self.volume = volume.min(MAX_VOLUME);
+        if self.muted {
+            return Ok(());
+        }
+        self.ramp_to(self.volume).await
+    }
+
+    pub async fn volume_up(&mut self) -> Result<(), Error> {
+        self.set_volume(self.volume.saturating_add(1)).await
+    }
+
+    pub async fn volume_down(&mut self) -> Result<(), Error> {
+        self.set_volume(self.volume.saturating_sub(1)).await
+    }
+
+    pub async fn set_mute(&mut self, mute: bool) -> Result<(), Error> {
+        if mute == self.muted {
+            return Ok(());
+        }
+        if mute {
+            self.fade_out().await?;
+            self.muted = true;
+            Ok(())
+        } else {
+            self.muted = false;
+            self.fade_in().await
+        }
+    }
+
+    pub async fn toggle_mute(&mut self) -> Result<(), Error> {
+        self.set_mute(!self.muted).await
+    }
+
+    /// 渐弱后换台，再渐强到原来的音量
+    pub async fn tune(&mut self, freq: u32) -> Result<(), Error> {
+        self.fade_out().await?;
+        let result = self.call(|tuner| tuner.rda5807m.set_frequency(freq)).await;
+        if result.is_ok() {
+            self.frequency = freq;
+        }
+        Timer::after(TUNE_DELAY).await;
+        self.fade_in().await?;
+        result
+    }
+
+    /// 音量降到最低后关闭音频输出(DMUTE)
+    pub async fn fade_out(&mut self) -> Result<(), Error> {
+        if self.muted {
+            return Ok(());
+        }
+        self.ramp_to(0).await?;
+        self.set_dmute(true).await
+    }
+
+    /// 打开音频输出(DMUTE)后音量渐强到设置值，静音状态下什么都不做
+    pub async fn fade_in(&mut self) -> Result<(), Error> {
+        if self.muted {
+            return Ok(());
+        }
+        self.set_dmute(false).await?;
+        self.ramp_to(self.volume).await
+    }
+
+    /// 渐弱后清除 ENABLE 位关闭芯片，之后需要重新调用 [`Tuner::start`]
+    pub async fn power_down(&mut self) -> Result<(), Error> {
+        self.fade_out().await?;
+        self.call(|tuner| tuner.rda5807m.stop()).await
+    }
+
+    /// 读取一组新的 RDS 数据，没有新数据时返回 `None`
+    #[cfg(feature = "rds")]
+    pub async fn rds_group(&mut self) -> Result<Option<Group>, Error> {
+        self.call(|tuner| {
+            let status = tuner.read_register(Register::RDA5807M_REG_STATUS)?;
+            if status & StatusBitFlag::RDSR == 0 {
+                return Ok(None);
+            }
+            let errors = tuner.read_register(Register::RDA5807M_REG_RSSI)?;
+            let mut blocks = [0; 4];
+            for (i, block) in blocks.iter_mut().enumerate() {
+                *block = tuner.read_register(Register::RDA5807M_REG_RDSA + i as u8)?;
+            }
+            Ok(Some(Group {
+                blocks,
+                block_errors: [
+                    ((errors >> BLERA_SHIFT) & BLER_MASK) as u8,
+                    (errors & BLER_MASK) as u8,
+                ],
+            }))
+        })
+        .await
+    }
+
+    pub async fn set_audio(&mut self, audio: &AudioConfig) -> Result<(), Error> {
+        self.audio = *audio;
+        self.call(|tuner| tuner.apply_audio()).await
+    }
+
+    /// 重新启动芯片，恢复频率、音量、静音、搜台阈值和音频设置
+    pub fn reinit(&mut self) -> Result<(), Error> {
+        self.rda5807m.start()?;
+        self.apply_audio()?;
+        self.rda5807m.set_seek_threshold(self.seek_threshold)?;
+        if self.frequency != 0 {
+            self.rda5807m.set_frequency(self.frequency)?;
+        }
+        self.rda5807m.set_volume(self.output)?;
+        self.rda5807m.mute(!self.output_muted)?;
+        Ok(())
+    }
+
+    async fn ramp_to(&mut self, target: u8) -> Result<(), Error> {
+        while self.output != target {
+            let output = if self.output < target {
+                self.output + 1
+            } else {
+                self.output - 1
+            };
+            self.call(|tuner| tuner.rda5807m.set_volume(output)).await?;
+            self.output = output;
+            Timer::after(RAMP_STEP_DELAY).await;
+        }
+        Ok(())
+    }
+
+    async fn set_dmute(&mut self, mute: bool) -> Result<(), Error> {
+        // 驱动的参数直接写入 DMUTE 位，DMUTE 为 1 时正常输出，为 0 时静音
+        self.call(|tuner| tuner.rda5807m.mute(!mute)).await?;
+        self.output_muted = mute;
+        Ok(())
+    }
+
+    /// 偶发错误等待后重试，连续失败的操作达到次数后重新初始化芯片
+    async fn call<T>(
+        &mut self,
+        mut operation: impl FnMut(&mut Self) -> Result<T, rda5807m::Error<E>>,
+    ) -> Result<T, Error> {
+        let mut backoff = self.policy.backoff;
+        let mut attempt = 1;
+        loop {
+            let error = match operation(self) {
+                Ok(value) => {
+                    self.failures = 0;
+                    return Ok(value);
+                }
+                Err(e) => Error::from(e),
+            };
+            if error.is_transient() && attempt < self.policy.attempts {
+                attempt += 1;
+                Timer::after(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+            self.failures = self.failures.saturating_add(1);
+            if self.failures >= self.policy.reinit_after {
+                self.failures = 0;
+                // 重新初始化失败时仍然返回原来的错误
+                self.reinit().ok();
+            }
+            return Err(error);
+        }
+    }
+
+    fn apply_audio(&mut self) -> Result<(), rda5807m::Error<E>> {
+        let audio = self.audio;
+        let config =
+            flag(audio.mono, ConfigBitFlags::MONO) | flag(audio.bass, ConfigBitFlags::BASS);
+        self.update_register(
+            Register::RDA5807M_REG_CONFIG,
+            ConfigBitFlags::MONO | ConfigBitFlags::BASS,
+            config,
+        )?;
+        self.update_register(
+            Register::RDA5807M_REG_GPIO,
+            SOFTMUTE_EN,
+            flag(audio.soft_mute, SOFTMUTE_EN),
+        )?;
+        self.update_register(
+            Register::RDA5807M_REG_BLEND,
+            SOFTBLEND_EN,
+            flag(audio.soft_blend, SOFTBLEND_EN),
+        )
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u16, rda5807m::Error<E>> {
+        let mut data = [0; 2];
+        self.i2c
+            .write_read(RANDOM_ADDRESS, &[register], &mut data)
+            .map_err(rda5807m::Error::I2C)?;
+        Ok(((data[0] as u16) << 8) | data[1] as u16)
+    }
+
+    fn update_register(
+        &mut self,
+        register: u8,
+        mask: u16,
+        value: u16,
+    ) -> Result<(), rda5807m::Error<E>> {
+        let old = self.read_register(register)?;
+        let new = (value & mask) | (old & !mask);
+        self.i2c
+            .write(RANDOM_ADDRESS, &[register, (new >> 8) as u8, new as u8])
+            .map_err(rda5807m::Error::I2C)
+    }
+}
+
+fn flag(enabled: bool, bit: u16) -> u16 {
+    if enabled {
+        bit
+    } else {
+        0
+    }
+}
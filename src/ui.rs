@@ -0,0 +1,323 @@
+use core::fmt::{self, Write};
+
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::primitives::{Line, Primitive, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics::Drawable;
+
+use crate::error::{Error, I2cFault};
+use crate::sleep::Remaining;
+use crate::tuner::{MAX_SEEK_THRESHOLD, MAX_VOLUME};
+
+/// 屏幕底部的音量条位置
+pub const VOLUME_BAR: Rectangle = Rectangle::new(Point::new(0, 55), Size::new(128, 9));
+/// 主界面显示的内容
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct MainScreen<'a> {
+    /// 频率(kHz)
+    pub frequency: u32,
+    pub rssi: u8,
+    pub stereo: bool,
+    pub volume: u8,
+    pub muted: bool,
+    /// RDS 节目名
+    pub program_service: Option<&'a str>,
+    /// RDS 节目类型
+    pub program_type: Option<&'a str>,
+    /// 睡眠定时剩余时间
+    pub sleep: Option<Remaining>,
+    /// 电池电量百分比，没有电池检测时为 None
+    pub battery: Option<u8>,
+    /// 电量低，代替节目类型显示提示
+    pub battery_low: bool,
+}
+
+/// 搜台设置页底部的信号强度表
+pub const RSSI_GAUGE: Rectangle = Rectangle::new(Point::new(0, 50), Size::new(128, 14));
+/// RSSI 寄存器 7 位
+const MAX_RSSI: u8 = 127;
+
+/// 屏幕右上角的立体声指示
+pub const STEREO_INDICATOR: Rectangle = Rectangle::new(Point::new(114, 0), Size::new(14, 11));
+
+/// 立体声指示左边的电池图标，不含右侧的正极
+pub const BATTERY_ICON: Rectangle = Rectangle::new(Point::new(96, 1), Size::new(14, 9));
+
+/// 按电量比例填充的电池图标
+pub fn draw_battery_icon<D>(display: &mut D, percent: u8) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    BATTERY_ICON
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(display)?;
+    let top_right = BATTERY_ICON.top_left + Point::new(BATTERY_ICON.size.width as i32, 0);
+    Rectangle::new(top_right + Point::new(0, 2), Size::new(2, 5))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)?;
+    let inner = BATTERY_ICON.offset(-2);
+    let width = inner.size.width * percent.min(100) as u32 / 100;
+    Rectangle::new(inner.top_left, Size::new(width, inner.size.height))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)
+}
+
+/// 按音量比例填充的音量条，静音时画一条斜线
+pub fn draw_volume_bar<D>(display: &mut D, volume: u8, muted: bool) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    VOLUME_BAR
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(display)?;
+    if muted {
+        return Line::new(
+            VOLUME_BAR.top_left,
+            VOLUME_BAR.bottom_right().unwrap_or(VOLUME_BAR.top_left),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(display);
+    }
+    let inner = VOLUME_BAR.offset(-2);
+    let width = inner.size.width * volume.min(MAX_VOLUME) as u32 / MAX_VOLUME as u32;
+    Rectangle::new(inner.top_left, Size::new(width, inner.size.height))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)
+}
+
+/// 收到立体声时反色显示 ST，否则显示 MO
+pub fn draw_stereo_indicator<D>(display: &mut D, stereo: bool) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let (text_color, background) = if stereo {
+        (BinaryColor::Off, BinaryColor::On)
+    } else {
+        (BinaryColor::On, BinaryColor::Off)
+    };
+    STEREO_INDICATOR
+        .into_styled(PrimitiveStyle::with_fill(background))
+        .draw(display)?;
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(text_color)
+        .build();
+    let label = if stereo { "ST" } else { "MO" };
+    Text::with_baseline(
+        label,
+        STEREO_INDICATOR.top_left + Point::new(1, 1),
+        style,
+        Baseline::Top,
+    )
+    .draw(display)?;
+    Ok(())
+}
+
+/// 按比例填充当前 RSSI，竖线标出搜台阈值的位置
+///
+/// SEEKTH 与 RSSI 量纲不同，这里按各自满量程换算，只用来对比强弱
+pub fn draw_rssi_gauge<D>(display: &mut D, rssi: u8, threshold: u8) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    RSSI_GAUGE
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(display)?;
+    let inner = RSSI_GAUGE.offset(-2);
+    let width = inner.size.width * rssi.min(MAX_RSSI) as u32 / MAX_RSSI as u32;
+    Rectangle::new(inner.top_left, Size::new(width, inner.size.height))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)?;
+    // 阈值线画在填充区域上时反色，保证始终可见
+    let x = inner.top_left.x
+        + (inner.size.width * threshold.min(MAX_SEEK_THRESHOLD) as u32 / MAX_SEEK_THRESHOLD as u32)
+            as i32;
+    let color = if x < inner.top_left.x + width as i32 {
+        BinaryColor::Off
+    } else {
+        BinaryColor::On
+    };
+    Line::new(
+        Point::new(x, RSSI_GAUGE.top_left.y),
+        Point::new(x, RSSI_GAUGE.top_left.y + RSSI_GAUGE.size.height as i32 - 1),
+    )
+    .into_styled(PrimitiveStyle::with_stroke(color, 1))
+    .draw(display)
+}
+
+/// 反色标题栏加错误原因，代替 panic 提示用户
+pub fn draw_error_screen<D>(display: &mut D, error: &Error) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let title = Rectangle::new(Point::zero(), Size::new(128, 11));
+    title
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)?;
+    let inverted = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(BinaryColor::Off)
+        .build();
+    let normal = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+    Text::with_baseline("Error", Point::new(1, 1), inverted, Baseline::Top).draw(display)?;
+    let (source, reason) = match error {
+        Error::Tuner(fault) => ("Tuner", i2c_fault_label(*fault)),
+        Error::Display => ("Display", ""),
+        Error::Storage => ("Storage", ""),
+    };
+    Text::with_baseline(source, Point::new(0, 16), normal, Baseline::Top).draw(display)?;
+    Text::with_baseline(reason, Point::new(0, 28), normal, Baseline::Top).draw(display)?;
+    Text::with_baseline("Retrying...", Point::new(0, 44), normal, Baseline::Top).draw(display)?;
+    Ok(())
+}
+
+fn i2c_fault_label(fault: I2cFault) -> &'static str {
+    match fault {
+        I2cFault::Nack => "No ACK",
+        I2cFault::Timeout => "Timeout",
+        I2cFault::Bus => "Bus busy",
+        I2cFault::Other => "I2C error",
+    }
+}
+
+/// 大字体显示频率，下面是节目名和信号强度，底部是音量条，右上角是电池和睡眠定时
+pub fn draw_main_screen<D>(display: &mut D, screen: &MainScreen) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let large = MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(BinaryColor::On)
+        .build();
+    let small = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+    let mut text = TextBuf::<16>::new();
+    write!(
+        text,
+        "{}.{}",
+        screen.frequency / 1000,
+        screen.frequency % 1000 / 100
+    )
+    .ok();
+    let next =
+        Text::with_baseline(text.as_str(), Point::zero(), large, Baseline::Top).draw(display)?;
+    Text::with_baseline("MHz", next + Point::new(2, 8), small, Baseline::Top).draw(display)?;
+    draw_stereo_indicator(display, screen.stereo)?;
+    if let Some(percent) = screen.battery {
+        draw_battery_icon(display, percent)?;
+    }
+    if let Some(remaining) = screen.sleep {
+        // 右对齐显示在立体声指示下方
+        let mut text = TextBuf::<8>::new();
+        write!(text, "{}", remaining).ok();
+        let x = 128 - (text.as_str().len() as i32) * 6;
+        Text::with_baseline(text.as_str(), Point::new(x, 12), small, Baseline::Top)
+            .draw(display)?;
+    }
+    if let Some(name) = screen.program_service {
+        Text::with_baseline(name, Point::new(0, 22), large, Baseline::Top).draw(display)?;
+    }
+    let mut text = TextBuf::<24>::new();
+    write!(text, "RSSI {}", screen.rssi).ok();
+    Text::with_baseline(text.as_str(), Point::new(0, 43), small, Baseline::Top).draw(display)?;
+    let label = if screen.battery_low {
+        Some("LOW BAT")
+    } else {
+        screen.program_type
+    };
+    if let Some(label) = label {
+        Text::with_baseline(label, Point::new(72, 43), small, Baseline::Top).draw(display)?;
+    }
+    draw_volume_bar(display, screen.volume, screen.muted)
+}
+
+/// 屏保的大小，位置在屏幕内来回移动
+const SAVER_SIZE: Size = Size::new(50, 32);
+
+/// 屏保：时间(没有时显示频率)和频率，每一步换一个位置
+pub fn draw_screensaver<D>(
+    display: &mut D,
+    time: Option<(u8, u8)>,
+    frequency: u32,
+    step: u32,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let large = MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(BinaryColor::On)
+        .build();
+    let small = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+    let origin = Point::new(
+        bounce(step * 7, 128 - SAVER_SIZE.width),
+        bounce(step * 5, 64 - SAVER_SIZE.height),
+    );
+    let mut line = TextBuf::<16>::new();
+    let mut caption = TextBuf::<16>::new();
+    let (mhz, khz) = (frequency / 1000, frequency % 1000 / 100);
+    match time {
+        Some((hour, minute)) => {
+            write!(line, "{:02}:{:02}", hour, minute).ok();
+            write!(caption, "{}.{} MHz", mhz, khz).ok();
+        }
+        None => {
+            write!(line, "{}.{}", mhz, khz).ok();
+            write!(caption, "MHz").ok();
+        }
+    }
+    Text::with_baseline(line.as_str(), origin, large, Baseline::Top).draw(display)?;
+    let caption_at = origin + Point::new(0, 21);
+    Text::with_baseline(caption.as_str(), caption_at, small, Baseline::Top).draw(display)?;
+    Ok(())
+}
+
+/// 在 0..=max 之间来回
+fn bounce(value: u32, max: u32) -> i32 {
+    let value = value % (2 * max);
+    if value > max {
+        (2 * max - value) as i32
+    } else {
+        value as i32
+    }
+}
+
+/// 格式化短文字用的定长缓冲，超出部分丢弃
+struct TextBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> TextBuf<N> {
+    fn new() -> Self {
+        TextBuf {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+impl<const N: usize> Write for TextBuf<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(N - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+use core::fmt::{self, Write};
+
+/// 格式有不兼容的修改时加一
+pub const SCHEMA_VERSION: u8 = 1;
+
+/// 给电脑记录和分析用的遥测数据，每条一行 JSON
+///
+/// 和普通日志混在同一个串口里，以 `{` 开头的行是遥测数据，其余的行可以忽略。
+/// 每条都有 `t`(类型)和 `ms`(开机后的毫秒数)：
+///
+/// ```text
+/// {"t":"hello","ms":0,"v":1}
+/// {"t":"status","ms":1000,"freq":98500,"rssi":42,"stereo":true,"volume":8,"muted":false}
+/// {"t":"rds","ms":1200,"pi":4660,"pty":10,"ps":"RADIO 1 "}
+/// {"t":"input","ms":1500,"source":"Ec11","kind":"EC11Front","velocity":3.5}
+/// ```
+///
+/// - `hello`：打开遥测时发送一次，`v` 是格式的版本
+/// - `status`：定时发送，`freq` 单位 kHz
+/// - `rds`：节目名或节目类型变化时发送，没有节目名时 `ps` 为 null
+/// - `input`：按键和旋钮事件，名称和固件的 `InputId`、`EventType` 一致
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Record<'a> {
+    Hello,
+    Status {
+        /// 频率(kHz)
+        frequency: u32,
+        rssi: u8,
+        stereo: bool,
+        volume: u8,
+        muted: bool,
+    },
+    Rds {
+        pi: u16,
+        pty: u8,
+        program_service: Option<&'a str>,
+    },
+    Input {
+        source: &'a str,
+        kind: &'a str,
+        velocity: f32,
+    },
+}
+
+impl Record<'_> {
+    /// 写出一行 JSON，不包括换行
+    pub fn write<W: Write>(&self, uptime_ms: u64, w: &mut W) -> fmt::Result {
+        match self {
+            Record::Hello => write!(
+                w,
+                r#"{{"t":"hello","ms":{},"v":{}"#,
+                uptime_ms, SCHEMA_VERSION
+            )?,
+            Record::Status {
+                frequency,
+                rssi,
+                stereo,
+                volume,
+                muted,
+            } => write!(
+                w,
+                r#"{{"t":"status","ms":{},"freq":{},"rssi":{},"stereo":{},"volume":{},"muted":{}"#,
+                uptime_ms, frequency, rssi, stereo, volume, muted
+            )?,
+            Record::Rds {
+                pi,
+                pty,
+                program_service,
+            } => {
+                write!(
+                    w,
+                    r#"{{"t":"rds","ms":{},"pi":{},"pty":{},"ps":"#,
+                    uptime_ms, pi, pty
+                )?;
+                match program_service {
+                    Some(ps) => write_string(w, ps)?,
+                    None => w.write_str("null")?,
+                }
+            }
+            Record::Input {
+                source,
+                kind,
+                velocity,
+            } => {
+                write!(w, r#"{{"t":"input","ms":{},"source":"#, uptime_ms)?;
+                write_string(w, source)?;
+                w.write_str(r#","kind":"#)?;
+                write_string(w, kind)?;
+                // NaN 不是合法的 JSON 数字
+                let velocity = if velocity.is_finite() { *velocity } else { 0.0 };
+                write!(w, r#","velocity":{:.1}"#, velocity)?;
+            }
+        }
+        w.write_char('}')
+    }
+}
+
+/// 带引号的 JSON 字符串
+fn write_string<W: Write>(w: &mut W, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::String;
+
+    fn line(record: Record, uptime_ms: u64) -> String {
+        let mut line = String::new();
+        record.write(uptime_ms, &mut line).unwrap();
+        line
+    }
+
+    /// 和文档里的示例一致
+    #[test]
+    fn examples() {
+        assert_eq!(line(Record::Hello, 0), r#"{"t":"hello","ms":0,"v":1}"#);
+        let status = Record::Status {
+            frequency: 98_500,
+            rssi: 42,
+            stereo: true,
+            volume: 8,
+            muted: false,
+        };
+        assert_eq!(
+            line(status, 1000),
+            r#"{"t":"status","ms":1000,"freq":98500,"rssi":42,"stereo":true,"volume":8,"muted":false}"#
+        );
+        let rds = Record::Rds {
+            pi: 0x1234,
+            pty: 10,
+            program_service: Some("RADIO 1 "),
+        };
+        assert_eq!(
+            line(rds, 1200),
+            r#"{"t":"rds","ms":1200,"pi":4660,"pty":10,"ps":"RADIO 1 "}"#
+        );
+        let input = Record::Input {
+            source: "Ec11",
+            kind: "EC11Front",
+            velocity: 3.5,
+        };
+        assert_eq!(
+            line(input, 1500),
+            r#"{"t":"input","ms":1500,"source":"Ec11","kind":"EC11Front","velocity":3.5}"#
+        );
+    }
+
+    #[test]
+    fn program_service() {
+        let rds = |program_service| Record::Rds {
+            pi: 1,
+            pty: 0,
+            program_service,
+        };
+        assert_eq!(
+            line(rds(None), 0),
+            r#"{"t":"rds","ms":0,"pi":1,"pty":0,"ps":null}"#
+        );
+        assert_eq!(
+            line(rds(Some("\"A\\\x07")), 0),
+            r#"{"t":"rds","ms":0,"pi":1,"pty":0,"ps":"\"A\\\u0007"}"#
+        );
+    }
+
+    #[test]
+    fn velocity_is_a_number() {
+        for velocity in [f32::NAN, f32::INFINITY] {
+            let input = Record::Input {
+                source: "Sw1",
+                kind: "KeyShort",
+                velocity,
+            };
+            assert!(line(input, 0).ends_with(r#""velocity":0.0}"#));
+        }
+    }
+}